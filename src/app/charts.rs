@@ -0,0 +1,178 @@
+use crate::app::series::{Series, SAMPLE_INTERVAL};
+use crate::app::App;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset, GraphType};
+use ratatui::Frame;
+use strum::Display;
+
+/// How much history the CHARTS tab plots.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+pub enum ChartWindow {
+    #[strum(to_string = "1m")]
+    OneMinute,
+    #[default]
+    #[strum(to_string = "5m")]
+    FiveMinutes,
+    #[strum(to_string = "15m")]
+    FifteenMinutes,
+    #[strum(to_string = "whole run")]
+    WholeRun,
+}
+
+impl ChartWindow {
+    pub fn wider(self) -> Self {
+        match self {
+            Self::OneMinute => Self::FiveMinutes,
+            Self::FiveMinutes => Self::FifteenMinutes,
+            Self::FifteenMinutes | Self::WholeRun => Self::WholeRun,
+        }
+    }
+
+    pub fn narrower(self) -> Self {
+        match self {
+            Self::OneMinute | Self::FiveMinutes => Self::OneMinute,
+            Self::FifteenMinutes => Self::FiveMinutes,
+            Self::WholeRun => Self::FifteenMinutes,
+        }
+    }
+
+    fn samples(self) -> usize {
+        let seconds = match self {
+            Self::OneMinute => 60,
+            Self::FiveMinutes => 5 * 60,
+            Self::FifteenMinutes => 15 * 60,
+            Self::WholeRun => return usize::MAX,
+        };
+        (seconds / SAMPLE_INTERVAL.as_secs()) as usize
+    }
+}
+
+impl App {
+    /// Reads and writes overlaid on charts with time and value axes, over the
+    /// selected window of history.
+    pub fn render_charts(&self, frame: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Ratio(1, 3); 3])
+            .split(area);
+
+        self.render_chart(
+            frame,
+            chunks[0],
+            "Throughput",
+            "ops/s",
+            &self.writes,
+            &self.reads,
+        );
+        self.render_chart(
+            frame,
+            chunks[1],
+            "Latency",
+            "ms",
+            &self.write_latency_avg_ms,
+            &self.read_latency_avg_ms,
+        );
+        self.render_chart(
+            frame,
+            chunks[2],
+            "Errors",
+            "errors/s",
+            &self.write_errors,
+            &self.read_errors,
+        );
+    }
+
+    fn render_chart(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        unit: &str,
+        writes: &Series,
+        reads: &Series,
+    ) {
+        let samples = self.chart_window.samples();
+        // Braille packs two points into each terminal column.
+        let width = area.width.saturating_sub(2) as usize * 2;
+        let write_points = writes.points(samples, width);
+        let read_points = reads.points(samples, width);
+
+        let end = writes.end().max(reads.end());
+        let start = write_points
+            .first()
+            .into_iter()
+            .chain(read_points.first())
+            .map(|(x, _)| *x)
+            .fold(end, f64::min);
+        let max = write_points
+            .iter()
+            .chain(&read_points)
+            .map(|(_, y)| *y)
+            .fold(0.0, f64::max)
+            .max(1.0)
+            * 1.1;
+
+        let datasets = vec![
+            Dataset::default()
+                .name(format!("Writes {} {}", writes.last().unwrap_or(&0), unit))
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Green))
+                .data(&write_points),
+            Dataset::default()
+                .name(format!("Reads {} {}", reads.last().unwrap_or(&0), unit))
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::LightBlue))
+                .data(&read_points),
+        ];
+        let chart = Chart::new(datasets)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "{}, last {} (z/Z: zoom out/in)",
+                        title, self.chart_window
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .x_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds([start, end.max(start + 1.0)])
+                    .labels([
+                        format_elapsed(start),
+                        format_elapsed((start + end) / 2.0),
+                        format_elapsed(end),
+                    ]),
+            )
+            .y_axis(
+                Axis::default()
+                    .title(unit)
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds([0.0, max])
+                    .labels([
+                        "0".to_string(),
+                        format!("{:.0}", max / 2.0),
+                        format!("{:.0}", max),
+                    ]),
+            );
+        frame.render_widget(chart, area);
+    }
+}
+
+/// Time into the run, as m:ss or h:mm:ss.
+fn format_elapsed(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
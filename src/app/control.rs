@@ -0,0 +1,167 @@
+use crate::Opt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+/// How often paused workers and the worker spawners check for changes.
+pub const CONTROL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The distributions --distribution accepts, in the order `d` cycles through them.
+const DISTRIBUTIONS: [&str; 7] = [
+    "sequential",
+    "uniform",
+    "normal",
+    "poisson",
+    "geometric",
+    "binomial",
+    "zipf",
+];
+
+/// How much each press of `+` or `-` changes the target rate by.
+const RATE_STEP: f64 = 1.25;
+
+/// Load settings that start out as given on the command line and can be
+/// changed from the TUI while the run goes on. The read and write tasks read
+/// them on every request.
+#[derive(Debug, Default)]
+pub struct Controls {
+    paused: AtomicBool,
+    rate_min: AtomicU64,
+    rate_max: AtomicU64,
+    readers: AtomicU64,
+    writers: AtomicU64,
+    distribution: AtomicUsize,
+}
+
+impl Controls {
+    pub fn new(opt: &Opt) -> Self {
+        let distribution = DISTRIBUTIONS
+            .iter()
+            .position(|distribution| *distribution == opt.distribution)
+            .unwrap_or(1);
+        Self {
+            paused: AtomicBool::new(false),
+            rate_min: AtomicU64::new(opt.rate_min),
+            rate_max: AtomicU64::new(opt.rate_max),
+            readers: AtomicU64::new(opt.readers as u64),
+            writers: AtomicU64::new(opt.writers as u64),
+            distribution: AtomicUsize::new(distribution),
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_paused(&self) {
+        self.paused.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    /// Returns once load is resumed or the run is cancelled.
+    pub async fn wait_while_paused(&self, cancellation_token: &CancellationToken) {
+        while self.paused() && !cancellation_token.is_cancelled() {
+            time::sleep(CONTROL_POLL_INTERVAL).await;
+        }
+    }
+
+    pub fn rate_min(&self) -> u64 {
+        self.rate_min.load(Ordering::Relaxed)
+    }
+
+    /// The target rate in requests per second, or 0 when unlimited.
+    pub fn rate_max(&self) -> u64 {
+        self.rate_max.load(Ordering::Relaxed)
+    }
+
+    /// Raises the rate range by a step. An unlimited rate stays unlimited.
+    pub fn raise_rate(&self) {
+        let rate_max = self.rate_max();
+        if rate_max == 0 {
+            return;
+        }
+        self.set_rate(step_up(self.rate_min()), step_up(rate_max));
+    }
+
+    /// Lowers the rate range by a step, down to 1 request per second. An
+    /// unlimited rate is first limited to `current`, the rate being achieved.
+    pub fn lower_rate(&self, current: u64) {
+        let rate_max = match self.rate_max() {
+            0 => current.max(1),
+            rate_max => rate_max,
+        };
+        self.set_rate(step_down(self.rate_min()), step_down(rate_max));
+    }
+
+    /// The target rate as shown in the TUI.
+    pub fn rate_description(&self) -> String {
+        match (self.rate_min(), self.rate_max()) {
+            (_, 0) => "unlimited".to_string(),
+            (0, rate_max) => format!("{}/s", rate_max),
+            (rate_min, rate_max) => format!("{}..{}/s", rate_min, rate_max),
+        }
+    }
+
+    fn set_rate(&self, rate_min: u64, rate_max: u64) {
+        self.rate_min
+            .store(rate_min.min(rate_max), Ordering::Relaxed);
+        self.rate_max.store(rate_max, Ordering::Relaxed);
+    }
+
+    /// The number of read workers the read task keeps running.
+    pub fn readers(&self) -> u64 {
+        self.readers.load(Ordering::Relaxed)
+    }
+
+    /// The number of write workers the write task keeps running.
+    pub fn writers(&self) -> u64 {
+        self.writers.load(Ordering::Relaxed)
+    }
+
+    pub fn add_reader(&self) {
+        self.readers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn remove_reader(&self) {
+        let _ = self
+            .readers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    pub fn add_writer(&self) {
+        self.writers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn remove_writer(&self) {
+        let _ = self
+            .writers
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    /// The distribution keys are drawn from.
+    pub fn distribution(&self) -> &'static str {
+        DISTRIBUTIONS[self.distribution.load(Ordering::Relaxed)]
+    }
+
+    pub fn next_distribution(&self) {
+        let _ = self
+            .distribution
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |index| {
+                Some((index + 1) % DISTRIBUTIONS.len())
+            });
+    }
+}
+
+fn step_up(rate: u64) -> u64 {
+    if rate == 0 {
+        return 0;
+    }
+    ((rate as f64 * RATE_STEP).round() as u64).max(rate + 1)
+}
+
+fn step_down(rate: u64) -> u64 {
+    if rate == 0 {
+        return 0;
+    }
+    ((rate as f64 / RATE_STEP).round() as u64).clamp(1, rate)
+}
@@ -0,0 +1,215 @@
+use crate::app::errors::Operation;
+use crate::app::histogram::Histogram;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Counters updated by the read and write tasks and sampled by the display task.
+#[derive(Debug, Default)]
+pub struct Counters {
+    readers_live: AtomicU64,
+    writers_live: AtomicU64,
+    bytes_written: AtomicU64,
+    bytes_read: AtomicU64,
+    reads: OperationCounters,
+    writes: OperationCounters,
+    deletes: AtomicU64,
+    lwt_applied: AtomicU64,
+    lwt_not_applied: AtomicU64,
+    lwt_timeouts: AtomicU64,
+    rows_written: AtomicU64,
+    view_lags: AtomicU64,
+    view_lag_us: AtomicU64,
+    batches: AtomicU64,
+    batch_latency_us: AtomicU64,
+}
+
+impl Counters {
+    pub fn worker_started(&self, operation: Operation) {
+        self.workers_live(operation).fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn worker_stopped(&self, operation: Operation) {
+        self.workers_live(operation).fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn workers_live(&self, operation: Operation) -> &AtomicU64 {
+        match operation {
+            Operation::Read => &self.readers_live,
+            Operation::Write => &self.writers_live,
+        }
+    }
+
+    pub fn readers_live(&self) -> u64 {
+        self.readers_live.load(Ordering::Relaxed)
+    }
+
+    pub fn writers_live(&self) -> u64 {
+        self.writers_live.load(Ordering::Relaxed)
+    }
+
+    pub fn add_bytes_written(&self, bytes: usize) {
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_bytes_read(&self, bytes: usize) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn operation(&self, operation: Operation) -> &OperationCounters {
+        match operation {
+            Operation::Read => &self.reads,
+            Operation::Write => &self.writes,
+        }
+    }
+
+    pub fn reads(&self) -> &OperationCounters {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &OperationCounters {
+        &self.writes
+    }
+
+    /// Records an acknowledged delete, each of which leaves a tombstone behind.
+    pub fn add_delete(&self) {
+        self.deletes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_lwt(&self, applied: bool) {
+        if applied {
+            self.lwt_applied.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.lwt_not_applied.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn add_lwt_timeout(&self) {
+        self.lwt_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_rows_written(&self, rows: usize) {
+        self.rows_written.fetch_add(rows as u64, Ordering::Relaxed);
+    }
+
+    /// Records how long after a base table write it became visible through an index or view.
+    pub fn add_view_lag(&self, lag: Duration) {
+        self.view_lags.fetch_add(1, Ordering::Relaxed);
+        self.view_lag_us
+            .fetch_add(lag.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_batch(&self, latency: Duration) {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+        self.batch_latency_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    pub fn deletes(&self) -> u64 {
+        self.deletes.load(Ordering::Relaxed)
+    }
+
+    pub fn lwt_applied(&self) -> u64 {
+        self.lwt_applied.load(Ordering::Relaxed)
+    }
+
+    pub fn lwt_not_applied(&self) -> u64 {
+        self.lwt_not_applied.load(Ordering::Relaxed)
+    }
+
+    pub fn lwt_timeouts(&self) -> u64 {
+        self.lwt_timeouts.load(Ordering::Relaxed)
+    }
+
+    pub fn rows_written(&self) -> u64 {
+        self.rows_written.load(Ordering::Relaxed)
+    }
+
+    pub fn view_lags(&self) -> u64 {
+        self.view_lags.load(Ordering::Relaxed)
+    }
+
+    pub fn view_lag_us(&self) -> u64 {
+        self.view_lag_us.load(Ordering::Relaxed)
+    }
+
+    pub fn batches(&self) -> u64 {
+        self.batches.load(Ordering::Relaxed)
+    }
+
+    pub fn batch_latency_us(&self) -> u64 {
+        self.batch_latency_us.load(Ordering::Relaxed)
+    }
+}
+
+/// Requests of one operation type, counted as skylar sends them rather than
+/// by the driver, whose split between unpaged and paged queries only matches
+/// writes and reads by coincidence.
+#[derive(Debug, Default)]
+pub struct OperationCounters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    rows: AtomicU64,
+    pages: AtomicU64,
+    latency_us: AtomicU64,
+    latency: Histogram,
+}
+
+impl OperationCounters {
+    /// Records a successful request, the number of rows it returned and how
+    /// long it took, including paging through them.
+    pub fn add_request(&self, rows: u64, latency: Duration) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.rows.fetch_add(rows, Ordering::Relaxed);
+        self.latency_us
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+        self.latency.record(latency);
+    }
+
+    /// Records a request that failed after any retries.
+    pub fn add_error(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a page of results fetched from a coordinator. Unpaged requests
+    /// and batches fetch exactly one.
+    pub fn add_page(&self) {
+        self.pages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Requests sent, whether they succeeded or failed.
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows.load(Ordering::Relaxed)
+    }
+
+    pub fn pages(&self) -> u64 {
+        self.pages.load(Ordering::Relaxed)
+    }
+
+    /// Total latency of successful requests.
+    pub fn latency_us(&self) -> u64 {
+        self.latency_us.load(Ordering::Relaxed)
+    }
+
+    /// Distribution of the latency of successful requests.
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+}
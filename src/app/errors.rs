@@ -0,0 +1,144 @@
+use scylla::transport::errors::{DbError, QueryError};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use strum::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    #[strum(to_string = "read")]
+    Read,
+    #[strum(to_string = "write")]
+    Write,
+}
+
+/// What went wrong with a request, by `QueryError` variant and, for errors the
+/// cluster returned, `DbError` variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    #[strum(to_string = "read timeout")]
+    ReadTimeout,
+    #[strum(to_string = "write timeout")]
+    WriteTimeout,
+    #[strum(to_string = "read failure")]
+    ReadFailure,
+    #[strum(to_string = "write failure")]
+    WriteFailure,
+    #[strum(to_string = "unavailable")]
+    Unavailable,
+    #[strum(to_string = "overloaded")]
+    Overloaded,
+    #[strum(to_string = "rate limited")]
+    RateLimited,
+    #[strum(to_string = "bootstrapping")]
+    Bootstrapping,
+    #[strum(to_string = "server error")]
+    ServerError,
+    #[strum(to_string = "invalid request")]
+    InvalidRequest,
+    #[strum(to_string = "client timeout")]
+    ClientTimeout,
+    #[strum(to_string = "connection broken")]
+    ConnectionBroken,
+    #[strum(to_string = "other")]
+    Other,
+}
+
+pub fn classify(error: &QueryError) -> ErrorClass {
+    match error {
+        QueryError::DbError(error, _) => match error {
+            DbError::ReadTimeout { .. } => ErrorClass::ReadTimeout,
+            DbError::WriteTimeout { .. } => ErrorClass::WriteTimeout,
+            DbError::ReadFailure { .. } => ErrorClass::ReadFailure,
+            DbError::WriteFailure { .. } => ErrorClass::WriteFailure,
+            DbError::Unavailable { .. } => ErrorClass::Unavailable,
+            DbError::Overloaded => ErrorClass::Overloaded,
+            DbError::RateLimitReached { .. } => ErrorClass::RateLimited,
+            DbError::IsBootstrapping => ErrorClass::Bootstrapping,
+            DbError::ServerError => ErrorClass::ServerError,
+            DbError::SyntaxError
+            | DbError::Invalid
+            | DbError::AlreadyExists { .. }
+            | DbError::FunctionFailure { .. }
+            | DbError::Unauthorized
+            | DbError::ConfigError
+            | DbError::Unprepared { .. } => ErrorClass::InvalidRequest,
+            _ => ErrorClass::Other,
+        },
+        QueryError::BadQuery(_) => ErrorClass::InvalidRequest,
+        QueryError::TimeoutError | QueryError::RequestTimeout(_) => ErrorClass::ClientTimeout,
+        QueryError::IoError(_)
+        | QueryError::TooManyOrphanedStreamIds(_)
+        | QueryError::UnableToAllocStreamId => ErrorClass::ConnectionBroken,
+        _ => ErrorClass::Other,
+    }
+}
+
+/// An error count, keyed by the coordinator it came from when the request
+/// reached one.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorCount {
+    pub operation: Operation,
+    pub class: ErrorClass,
+    pub node: Option<SocketAddr>,
+    pub count: u64,
+}
+
+impl ErrorCount {
+    fn key(&self) -> ErrorKey {
+        (self.operation, self.class, self.node)
+    }
+}
+
+/// The counts added since `earlier`, leaving out ones that didn't change.
+pub fn counts_since(counts: &[ErrorCount], earlier: &[ErrorCount]) -> Vec<ErrorCount> {
+    let earlier: BTreeMap<ErrorKey, u64> = earlier
+        .iter()
+        .map(|count| (count.key(), count.count))
+        .collect();
+    counts
+        .iter()
+        .map(|count| ErrorCount {
+            count: count.count - earlier.get(&count.key()).copied().unwrap_or(0),
+            ..count.clone()
+        })
+        .filter(|count| count.count > 0)
+        .collect()
+}
+
+type ErrorKey = (Operation, ErrorClass, Option<SocketAddr>);
+
+/// Failed attempt counts by operation, class and coordinator node. A request
+/// retried before it failed counts once per failed attempt.
+#[derive(Debug, Default)]
+pub struct Errors {
+    counts: Mutex<BTreeMap<ErrorKey, u64>>,
+}
+
+impl Errors {
+    pub fn add(&self, operation: Operation, class: ErrorClass, node: Option<SocketAddr>) {
+        *self
+            .counts
+            .lock()
+            .unwrap()
+            .entry((operation, class, node))
+            .or_default() += 1;
+    }
+
+    pub fn counts(&self) -> Vec<ErrorCount> {
+        self.counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&(operation, class, node), &count)| ErrorCount {
+                operation,
+                class,
+                node,
+                count,
+            })
+            .collect()
+    }
+}
@@ -1,14 +1,38 @@
+use crate::app::logging;
 use crate::app::state::AppState;
+use crate::app::tabs::SelectedTab;
 use crate::app::App;
 use ratatui::crossterm::event;
 use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
 use std::time::Duration;
+use tracing::{info, Level};
 
 impl App {
-    pub fn handle_events(&mut self) -> std::io::Result<()> {
-        if event::poll(Duration::from_millis(0))? {
+    /// Handles every key pressed since the last call, returning whether there
+    /// were any.
+    pub fn handle_events(&mut self) -> std::io::Result<bool> {
+        let mut handled = false;
+        while event::poll(Duration::from_millis(0))? {
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
+                    handled = true;
+                    if self.show_help {
+                        // Any key closes the help overlay, except that Ctrl-C still quits.
+                        self.show_help = false;
+                        if key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(event::KeyModifiers::CONTROL)
+                        {
+                            self.quit();
+                        }
+                        continue;
+                    }
+                    match self.selected_tab {
+                        SelectedTab::Charts => self.handle_charts_key(key.code),
+                        SelectedTab::Latency => self.handle_latency_key(key.code),
+                        SelectedTab::Logs => self.handle_logs_key(key.code),
+                        _ => {}
+                    }
+                    self.handle_control_key(key.code);
                     match key.code {
                         KeyCode::Char('l') | KeyCode::Right => self.next_tab(),
                         KeyCode::Char('h') | KeyCode::Left => self.previous_tab(),
@@ -24,7 +48,111 @@ impl App {
             }
         }
 
-        Ok(())
+        Ok(handled)
+    }
+
+    /// Runtime controls, which apply to the read and write workers on their
+    /// next request whatever tab is selected.
+    fn handle_control_key(&mut self, code: KeyCode) {
+        let controls = &self.controls;
+        match code {
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                controls.toggle_paused();
+                info!(
+                    "Load {}",
+                    if controls.paused() {
+                        "paused"
+                    } else {
+                        "resumed"
+                    }
+                );
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                controls.raise_rate();
+                info!("Rate {}", controls.rate_description());
+            }
+            KeyCode::Char('-') => {
+                let current = *self
+                    .writes
+                    .last()
+                    .unwrap_or(&0)
+                    .max(self.reads.last().unwrap_or(&0));
+                controls.lower_rate(current);
+                info!("Rate {}", controls.rate_description());
+            }
+            KeyCode::Char('r') => {
+                controls.add_reader();
+                info!("Readers {}", controls.readers());
+            }
+            KeyCode::Char('R') => {
+                controls.remove_reader();
+                info!("Readers {}", controls.readers());
+            }
+            KeyCode::Char('w') => {
+                controls.add_writer();
+                info!("Writers {}", controls.writers());
+            }
+            KeyCode::Char('W') => {
+                controls.remove_writer();
+                info!("Writers {}", controls.writers());
+            }
+            KeyCode::Char('d') => {
+                controls.next_distribution();
+                info!("Distribution {}", controls.distribution());
+            }
+            KeyCode::Char('x') => {
+                self.reset_metrics();
+                info!("Metrics reset");
+            }
+            KeyCode::Char('?') => self.show_help = true,
+            _ => {}
+        }
+    }
+
+    fn handle_charts_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('z') => self.chart_window = self.chart_window.wider(),
+            KeyCode::Char('Z') => self.chart_window = self.chart_window.narrower(),
+            _ => {}
+        }
+    }
+
+    fn handle_latency_key(&mut self, code: KeyCode) {
+        if code == KeyCode::Char('c') {
+            self.latency_view = self.latency_view.toggle();
+        }
+    }
+
+    /// Level filtering and scrollback on the LOGS tab. The scroll offset counts
+    /// lines up from the newest, so at 0 the tab follows new lines.
+    fn handle_logs_key(&mut self, code: KeyCode) {
+        const PAGE: usize = 20;
+        match code {
+            KeyCode::Char('f') => {
+                self.log_level = match self.log_level {
+                    Level::TRACE => Level::DEBUG,
+                    Level::DEBUG => Level::INFO,
+                    Level::INFO => Level::WARN,
+                    Level::WARN => Level::ERROR,
+                    _ => Level::TRACE,
+                };
+            }
+            KeyCode::Char('k') | KeyCode::Up => self.log_scroll += 1,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.log_scroll = self.log_scroll.saturating_sub(1)
+            }
+            KeyCode::PageUp => self.log_scroll += PAGE,
+            KeyCode::PageDown => self.log_scroll = self.log_scroll.saturating_sub(PAGE),
+            KeyCode::Char('g') | KeyCode::Home => self.log_scroll = usize::MAX,
+            KeyCode::Char('G') | KeyCode::End => self.log_scroll = 0,
+            _ => {}
+        }
+        let lines = logging::with_logs(|logs| {
+            logs.iter()
+                .filter(|line| line.level <= self.log_level)
+                .count()
+        });
+        self.log_scroll = self.log_scroll.min(lines.saturating_sub(1));
     }
 
     pub fn next_tab(&mut self) {
@@ -0,0 +1,223 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Latencies below this many microseconds get a bucket each.
+const EXACT: u64 = 32;
+/// Each doubling of latency above EXACT is split into this many buckets, so
+/// a bucket is at most 1/16th wider than its lower bound.
+const SUB_BUCKETS: u64 = 16;
+/// Latencies of 2^MAX_EXPONENT microseconds, about 12 days, and over share
+/// the last bucket.
+const MAX_EXPONENT: u64 = 40;
+const BUCKETS: usize = (EXACT + (MAX_EXPONENT - 5 + 1) * SUB_BUCKETS) as usize;
+
+/// A log-linear latency histogram in microseconds that request tasks record
+/// into without locking.
+#[derive(Debug)]
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    max_us: AtomicU64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            max_us: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&self, latency: Duration) {
+        let us = latency.as_micros() as u64;
+        self.buckets[bucket(us)].fetch_add(1, Ordering::Relaxed);
+        self.max_us.fetch_max(us, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            counts: self
+                .buckets
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+            max_us: self.max_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn bucket(us: u64) -> usize {
+    if us < EXACT {
+        return us as usize;
+    }
+    let exponent = (63 - us.leading_zeros() as u64).min(MAX_EXPONENT);
+    let top = (us >> (exponent - 4)).min(2 * SUB_BUCKETS - 1);
+    (EXACT + (exponent - 5) * SUB_BUCKETS + top - SUB_BUCKETS) as usize
+}
+
+/// The smallest latency in microseconds past a bucket's range.
+fn upper_bound(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < EXACT {
+        return bucket + 1;
+    }
+    let exponent = (bucket - EXACT) / SUB_BUCKETS + 5;
+    let top = (bucket - EXACT) % SUB_BUCKETS + SUB_BUCKETS;
+    (top + 1) << (exponent - 4)
+}
+
+/// Histogram counts at a point in time, or the difference between two.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    counts: Vec<u64>,
+    max_us: u64,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS],
+            max_us: 0,
+        }
+    }
+}
+
+impl Snapshot {
+    /// The latencies recorded since `earlier`. Its max is bounded by the
+    /// highest bucket recorded into, since the true max isn't kept per interval.
+    pub fn since(&self, earlier: &Snapshot) -> Snapshot {
+        let counts: Vec<u64> = self
+            .counts
+            .iter()
+            .zip(&earlier.counts)
+            .map(|(count, earlier)| count - earlier)
+            .collect();
+        let max_us = counts
+            .iter()
+            .rposition(|count| *count > 0)
+            .map(|bucket| (upper_bound(bucket) - 1).min(self.max_us))
+            .unwrap_or(0);
+        Snapshot { counts, max_us }
+    }
+
+    /// The latencies recorded in either snapshot.
+    pub fn combined(&self, other: &Snapshot) -> Snapshot {
+        Snapshot {
+            counts: self
+                .counts
+                .iter()
+                .zip(&other.counts)
+                .map(|(count, other)| count + other)
+                .collect(),
+            max_us: self.max_us.max(other.max_us),
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn max_us(&self) -> u64 {
+        self.max_us
+    }
+
+    /// The latency in microseconds that `percentile` percent of requests
+    /// finished within, to the upper bound of its bucket.
+    pub fn percentile_us(&self, percentile: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+        let rank = ((percentile / 100.0 * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, bucket_count) in self.counts.iter().enumerate() {
+            seen += bucket_count;
+            if seen >= rank {
+                return (upper_bound(bucket) - 1).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    /// Counts grouped by powers of two of microseconds, as (upper bound,
+    /// count) from the lowest group recorded into to the highest.
+    pub fn power_of_two_counts(&self) -> Vec<(u64, u64)> {
+        let groups = self.power_of_two_groups();
+        let first = groups.iter().position(|(_, count)| *count > 0);
+        let last = groups.iter().rposition(|(_, count)| *count > 0);
+        match (first, last) {
+            (Some(first), Some(last)) => groups[first..=last].to_vec(),
+            _ => vec![],
+        }
+    }
+
+    /// Counts grouped by powers of two of microseconds, as (upper bound,
+    /// count) over the histogram's whole range, so the bounds are the same
+    /// in every snapshot.
+    pub fn power_of_two_groups(&self) -> Vec<(u64, u64)> {
+        let mut groups: Vec<(u64, u64)> = vec![];
+        for (bucket, count) in self.counts.iter().enumerate() {
+            let bound = upper_bound(bucket).next_power_of_two();
+            match groups.last_mut() {
+                Some((last, total)) if *last == bound => *total += count,
+                _ => groups.push((bound, *count)),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_round_trip_through_their_bounds() {
+        for index in 0..BUCKETS - 1 {
+            let upper = upper_bound(index);
+            assert_eq!(bucket(upper - 1), index);
+            assert_eq!(bucket(upper), index + 1);
+        }
+    }
+
+    #[test]
+    fn bucket_boundaries() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(31), 31);
+        assert_eq!(upper_bound(31), 32);
+        assert_eq!(bucket(32), 32);
+        assert_eq!(bucket(33), 32);
+        assert_eq!(upper_bound(32), 34);
+        assert_eq!(bucket(1 << 40), BUCKETS - SUB_BUCKETS as usize);
+        assert_eq!(bucket((1 << 41) - 1), BUCKETS - 1);
+        assert_eq!(bucket(1 << 41), BUCKETS - 1);
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn percentiles_are_bounded_by_the_max() {
+        let histogram = Histogram::default();
+        assert_eq!(histogram.snapshot().percentile_us(50.0), 0);
+
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_micros(3000));
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 2);
+        assert_eq!(snapshot.percentile_us(50.0), 103);
+        assert_eq!(snapshot.percentile_us(100.0), 3000);
+        assert_eq!(snapshot.percentile_us(100.0), snapshot.max_us());
+    }
+
+    #[test]
+    fn interval_max_is_bounded_by_its_highest_bucket() {
+        let histogram = Histogram::default();
+        histogram.record(Duration::from_micros(3000));
+        let earlier = histogram.snapshot();
+        histogram.record(Duration::from_micros(100));
+        let interval = histogram.snapshot().since(&earlier);
+        assert_eq!(interval.count(), 1);
+        assert_eq!(interval.max_us(), 103);
+        assert_eq!(interval.percentile_us(100.0), 103);
+    }
+}
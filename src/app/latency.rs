@@ -0,0 +1,198 @@
+use crate::app::histogram::Snapshot;
+use crate::app::App;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{BarChart, Block, Borders, Row, Table};
+use ratatui::Frame;
+use strum::Display;
+
+const PERCENTILES: [(&str, f64); 6] = [
+    ("p50", 50.0),
+    ("p90", 90.0),
+    ("p99", 99.0),
+    ("p99.9", 99.9),
+    ("p99.99", 99.99),
+    ("p99.999", 99.999),
+];
+
+/// Whether the LATENCY tab shows the last sample interval, or everything since
+/// the run started or metrics were last reset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+pub enum LatencyView {
+    #[default]
+    #[strum(to_string = "last interval")]
+    Interval,
+    #[strum(to_string = "cumulative")]
+    Cumulative,
+}
+
+impl LatencyView {
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Interval => Self::Cumulative,
+            Self::Cumulative => Self::Interval,
+        }
+    }
+}
+
+/// Read and write latency distributions, as of the latest tick, over the
+/// tick before it, and as of the last reset.
+#[derive(Debug, Clone, Default)]
+pub struct LatencySnapshots {
+    pub reads: Snapshot,
+    pub writes: Snapshot,
+    pub read_interval: Snapshot,
+    pub write_interval: Snapshot,
+    read_reset: Snapshot,
+    write_reset: Snapshot,
+}
+
+impl LatencySnapshots {
+    pub fn reset(&mut self) {
+        self.read_reset = self.reads.clone();
+        self.write_reset = self.writes.clone();
+    }
+
+    /// Reads and writes together, since the last reset.
+    pub fn since_reset(&self) -> Snapshot {
+        self.reads
+            .since(&self.read_reset)
+            .combined(&self.writes.since(&self.write_reset))
+    }
+}
+
+impl App {
+    pub fn update_latency(&mut self) {
+        let reads = self.counters.reads().latency().snapshot();
+        let writes = self.counters.writes().latency().snapshot();
+        self.latency.read_interval = reads.since(&self.latency.reads);
+        self.latency.write_interval = writes.since(&self.latency.writes);
+        self.latency.reads = reads;
+        self.latency.writes = writes;
+
+        let latency_us = self.counters.reads().latency_us() + self.counters.writes().latency_us();
+        let since_reset = self.latency.since_reset();
+        self.latency_avg_ms.push(
+            (latency_us - self.baseline.latency_us)
+                .checked_div(since_reset.count())
+                .unwrap_or(0)
+                / 1000,
+        );
+        self.latency_percentile_ms
+            .push(since_reset.percentile_us(99.9) / 1000);
+    }
+
+    /// Latency distributions of reads and writes as bar charts, with the
+    /// tail percentiles that averages hide alongside.
+    pub fn render_latency(&self, frame: &mut Frame, area: Rect) {
+        let (writes, reads) = match self.latency_view {
+            LatencyView::Interval => (
+                self.latency.write_interval.clone(),
+                self.latency.read_interval.clone(),
+            ),
+            LatencyView::Cumulative => (
+                self.latency.writes.since(&self.latency.write_reset),
+                self.latency.reads.since(&self.latency.read_reset),
+            ),
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(32)].as_ref())
+            .split(area);
+        let charts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(chunks[0]);
+
+        self.render_distribution(frame, charts[0], "Write Latency", &writes, Color::Green);
+        self.render_distribution(frame, charts[1], "Read Latency", &reads, Color::LightBlue);
+
+        let header = Row::new(["Percentile", "Writes", "Reads"])
+            .style(Style::default().fg(Color::LightBlue));
+        let mut rows: Vec<Row> = PERCENTILES
+            .iter()
+            .map(|(name, percentile)| {
+                Row::new([
+                    name.to_string(),
+                    format_latency(writes.percentile_us(*percentile)),
+                    format_latency(reads.percentile_us(*percentile)),
+                ])
+            })
+            .collect();
+        rows.push(Row::new([
+            "max".to_string(),
+            format_latency(writes.max_us()),
+            format_latency(reads.max_us()),
+        ]));
+        rows.push(Row::new([
+            "count".to_string(),
+            writes.count().to_string(),
+            reads.count().to_string(),
+        ]));
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title(format!("Percentiles, {}", self.latency_view))
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[1]);
+    }
+
+    fn render_distribution(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        title: &str,
+        snapshot: &Snapshot,
+        color: Color,
+    ) {
+        let groups = snapshot.power_of_two_counts();
+        let labels: Vec<String> = groups
+            .iter()
+            .map(|(bound, _)| format!("<{}", format_latency(*bound)))
+            .collect();
+        let data: Vec<(&str, u64)> = labels
+            .iter()
+            .zip(&groups)
+            .map(|(label, (_, count))| (label.as_str(), *count))
+            .collect();
+        let width = area.width.saturating_sub(2) as usize;
+        let bar_width = (width / data.len().max(1)).saturating_sub(1).clamp(1, 9) as u16;
+
+        let chart = BarChart::default()
+            .block(
+                Block::default()
+                    .title(format!(
+                        "{}, {} (c: interval/cumulative)",
+                        title, self.latency_view
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .data(&data)
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(color))
+            .value_style(Style::default().fg(Color::Black).bg(color));
+        frame.render_widget(chart, area);
+    }
+}
+
+/// Microseconds as us, ms or s, whichever keeps the number short.
+fn format_latency(us: u64) -> String {
+    if us < 1000 {
+        format!("{}us", us)
+    } else if us < 1_000_000 {
+        format!("{:.1}ms", us as f64 / 1000.0)
+    } else {
+        format!("{:.1}s", us as f64 / 1_000_000.0)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::app::counters::Counters;
+use crate::app::errors::{classify, Errors, Operation};
+use crate::app::nodes::Nodes;
+use scylla::history::{AttemptId, HistoryListener, QueryId, SpeculativeId};
+use scylla::retry_policy::RetryDecision;
+use scylla::transport::errors::QueryError;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Set on every statement an operation runs, counts the pages it fetches,
+/// times each attempt by the coordinator that served it, and attributes
+/// errors to the coordinator that returned them: each failed attempt,
+/// including ones the retry policy went on to retry, and requests that failed
+/// before any attempt reached a node.
+#[derive(Debug)]
+pub struct RequestListener {
+    operation: Operation,
+    errors: Arc<Errors>,
+    counters: Arc<Counters>,
+    nodes: Arc<Nodes>,
+    next_id: AtomicUsize,
+    attempts: Mutex<HashMap<AttemptId, (QueryId, SocketAddr, Instant)>>,
+    failed_attempts: Mutex<HashSet<QueryId>>,
+}
+
+impl RequestListener {
+    pub fn new(
+        operation: Operation,
+        errors: Arc<Errors>,
+        counters: Arc<Counters>,
+        nodes: Arc<Nodes>,
+    ) -> Self {
+        Self {
+            operation,
+            errors,
+            counters,
+            nodes,
+            next_id: AtomicUsize::new(0),
+            attempts: Mutex::new(HashMap::new()),
+            failed_attempts: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn next_id(&self) -> usize {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl HistoryListener for RequestListener {
+    fn log_query_start(&self) -> QueryId {
+        QueryId(self.next_id())
+    }
+
+    fn log_query_success(&self, query_id: QueryId) {
+        // A paged request logs each page it fetches as a query of its own.
+        self.counters.operation(self.operation).add_page();
+        self.failed_attempts.lock().unwrap().remove(&query_id);
+        // Speculative attempts still in flight are dropped once another succeeds.
+        self.attempts
+            .lock()
+            .unwrap()
+            .retain(|_, (id, _, _)| *id != query_id);
+    }
+
+    fn log_query_error(&self, query_id: QueryId, error: &QueryError) {
+        // When the request timeout fires, the attempts still in flight are
+        // dropped without ending, so the timeout is filed under the nodes
+        // serving them.
+        let mut outstanding = vec![];
+        self.attempts.lock().unwrap().retain(|_, (id, node, _)| {
+            let keep = *id != query_id;
+            if !keep {
+                outstanding.push(*node);
+            }
+            keep
+        });
+        let failed_attempts = self.failed_attempts.lock().unwrap().remove(&query_id);
+        for node in &outstanding {
+            self.errors
+                .add(self.operation, classify(error), Some(*node));
+            self.nodes.add_error(*node, self.operation);
+        }
+        if outstanding.is_empty() && !failed_attempts {
+            self.errors.add(self.operation, classify(error), None);
+        }
+    }
+
+    fn log_new_speculative_fiber(&self, _query_id: QueryId) -> SpeculativeId {
+        SpeculativeId(self.next_id())
+    }
+
+    fn log_attempt_start(
+        &self,
+        query_id: QueryId,
+        _speculative_id: Option<SpeculativeId>,
+        node_addr: SocketAddr,
+    ) -> AttemptId {
+        let attempt_id = AttemptId(self.next_id());
+        self.attempts
+            .lock()
+            .unwrap()
+            .insert(attempt_id, (query_id, node_addr, Instant::now()));
+        attempt_id
+    }
+
+    fn log_attempt_success(&self, attempt_id: AttemptId) {
+        if let Some((_, node, started)) = self.attempts.lock().unwrap().remove(&attempt_id) {
+            self.nodes
+                .add_attempt(node, self.operation, started.elapsed());
+        }
+    }
+
+    fn log_attempt_error(
+        &self,
+        attempt_id: AttemptId,
+        error: &QueryError,
+        _retry_decision: &RetryDecision,
+    ) {
+        if let Some((query_id, node, _)) = self.attempts.lock().unwrap().remove(&attempt_id) {
+            self.failed_attempts.lock().unwrap().insert(query_id);
+            self.errors.add(self.operation, classify(error), Some(node));
+            self.nodes.add_error(node, self.operation);
+        }
+    }
+}
@@ -1,11 +1,142 @@
-pub fn init() {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::filter::EnvFilter::builder()
-                .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .with_ansi(cfg!(debug_assertions))
-        .compact()
-        .init()
+use crate::app::otel;
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{filter_fn, EnvFilter, LevelFilter};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+/// Log lines kept for the LOGS tab, the oldest dropped first.
+const LOG_CAPACITY: usize = 10_000;
+
+static LOGS: Lazy<Mutex<VecDeque<LogLine>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)));
+
+/// Set while the TUI owns the terminal, when writing to stderr would draw
+/// over it.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: DateTime<Utc>,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Logs to stderr while the TUI isn't running, to the LOGS tab's buffer, and
+/// to --log-file when set, each filtered by RUST_LOG.
+pub fn init(opt: &Opt) -> Result<()> {
+    if !matches!(opt.log_format.as_str(), "text" | "json") {
+        return Err(anyhow!("Unsupported log format: {}", opt.log_format));
+    }
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = vec![];
+
+    layers.push(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(cfg!(debug_assertions))
+            .with_writer(|| -> Box<dyn io::Write> {
+                if TUI_ACTIVE.load(Ordering::Relaxed) {
+                    Box::new(io::sink())
+                } else {
+                    Box::new(io::stderr())
+                }
+            })
+            .compact()
+            .with_filter(env_filter())
+            .boxed(),
+    );
+
+    layers.push(BufferLayer.with_filter(env_filter()).boxed());
+
+    if let Some(path) = &opt.log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("Error opening log file {}: {}", path.display(), e))?;
+        let layer = tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(Mutex::new(file));
+        layers.push(if opt.log_format == "json" {
+            layer.json().with_filter(env_filter()).boxed()
+        } else {
+            layer.with_filter(env_filter()).boxed()
+        });
+    }
+
+    // Operation spans are exported whatever the log level, and only they are.
+    if let Some(layer) = otel::layer() {
+        layers.push(
+            layer
+                .with_filter(filter_fn(|metadata| metadata.target() == otel::TARGET))
+                .boxed(),
+        );
+    }
+
+    tracing_subscriber::registry().with(layers).init();
+    Ok(())
+}
+
+fn env_filter() -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy()
+}
+
+pub fn set_tui_active(active: bool) {
+    TUI_ACTIVE.store(active, Ordering::Relaxed);
+}
+
+/// Calls `f` with the buffered log lines, oldest first.
+pub fn with_logs<T>(f: impl FnOnce(&VecDeque<LogLine>) -> T) -> T {
+    f(&LOGS.lock().unwrap())
+}
+
+/// Keeps each event, formatted as the message followed by its fields, in the
+/// LOGS tab's buffer.
+struct BufferLayer;
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        let line = LogLine {
+            time: Utc::now(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: message.0,
+        };
+        let mut logs = LOGS.lock().unwrap();
+        if logs.len() == LOG_CAPACITY {
+            logs.pop_front();
+        }
+        logs.push_back(line);
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
 }
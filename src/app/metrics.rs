@@ -1,52 +1,195 @@
+use crate::app::errors::{self, ErrorCount};
+use crate::app::series::Series;
 use crate::app::App;
 use anyhow::{anyhow, Result};
 use regex::Regex;
-use scylla::Metrics;
 use std::collections::HashMap;
 
+/// Run totals as of the last reset. The TUI shows totals relative to them,
+/// while the run summary and results file count from the start of the run.
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    pub latency_us: u64,
+    failed_requests: u64,
+    errors: Vec<ErrorCount>,
+    validation: [u64; 4],
+    deletes: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
 impl App {
-    pub fn update_metrics(&mut self, metrics: &Metrics) {
-        let queries_num_rate = metrics.get_queries_num() - self.queries_num_prev;
-        let queries_iter_num_rate = metrics.get_queries_iter_num() - self.queries_iter_num_prev;
-        let errors_num_rate = metrics.get_errors_num() - self.errors_num_prev;
-        let errors_iter_num_rate = metrics.get_errors_iter_num() - self.errors_iter_num_prev;
-
-        self.queries_num_prev = metrics.get_queries_num();
-        self.queries_iter_num_prev = metrics.get_queries_iter_num();
-        self.errors_num_prev = metrics.get_errors_num();
-        self.errors_iter_num_prev = metrics.get_errors_iter_num();
-
-        self.queries_num.push(queries_num_rate);
-        self.queries_iter_num.push(queries_iter_num_rate);
-        self.errors_num.push(errors_num_rate);
-        self.errors_iter_num.push(errors_iter_num_rate);
-        self.latency_avg_ms
-            .push(metrics.get_latency_avg_ms().unwrap_or(0));
-        self.latency_percentile_ms
-            .push(metrics.get_latency_percentile_ms(99.9).unwrap_or(0));
-
-        self.trim_metrics();
+    pub fn update_metrics(&mut self) {
+        let bytes_written = self.counters.bytes_written();
+        let bytes_read = self.counters.bytes_read();
+        self.bytes_written
+            .push(bytes_written - self.bytes_written_prev);
+        self.bytes_read.push(bytes_read - self.bytes_read_prev);
+        self.bytes_written_prev = bytes_written;
+        self.bytes_read_prev = bytes_read;
+
+        let reads = self.counters.reads().requests();
+        let read_errors = self.counters.reads().errors();
+        let read_pages = self.counters.reads().pages();
+        let rows_read = self.counters.reads().rows();
+        let read_latency_us = self.counters.reads().latency_us();
+        // Rows and latency are only counted for reads that succeeded.
+        let reads_succeeded = (reads - self.reads_prev) - (read_errors - self.read_errors_prev);
+        self.reads.push(reads - self.reads_prev);
+        self.read_errors.push(read_errors - self.read_errors_prev);
+        self.read_pages.push(read_pages - self.read_pages_prev);
+        self.rows_per_read.push(
+            (rows_read - self.rows_read_prev)
+                .checked_div(reads_succeeded)
+                .unwrap_or(0),
+        );
+        self.read_latency_avg_ms.push(
+            (read_latency_us - self.read_latency_us_prev)
+                .checked_div(reads_succeeded)
+                .unwrap_or(0)
+                / 1000,
+        );
+        self.reads_prev = reads;
+        self.read_errors_prev = read_errors;
+        self.read_pages_prev = read_pages;
+        self.rows_read_prev = rows_read;
+        self.read_latency_us_prev = read_latency_us;
+        self.deletes_prev = self.counters.deletes();
+
+        let lwt_applied = self.counters.lwt_applied();
+        let lwt_not_applied = self.counters.lwt_not_applied();
+        let lwt_timeouts = self.counters.lwt_timeouts();
+        self.lwt_applied.push(lwt_applied - self.lwt_applied_prev);
+        self.lwt_not_applied
+            .push(lwt_not_applied - self.lwt_not_applied_prev);
+        self.lwt_timeouts
+            .push(lwt_timeouts - self.lwt_timeouts_prev);
+        self.lwt_applied_prev = lwt_applied;
+        self.lwt_not_applied_prev = lwt_not_applied;
+        self.lwt_timeouts_prev = lwt_timeouts;
+
+        let writes = self.counters.writes().requests();
+        let write_errors = self.counters.writes().errors();
+        let write_latency_us = self.counters.writes().latency_us();
+        let writes_succeeded =
+            (writes - self.writes_prev) - (write_errors - self.write_errors_prev);
+        self.writes.push(writes - self.writes_prev);
+        self.write_errors
+            .push(write_errors - self.write_errors_prev);
+        self.write_latency_avg_ms.push(
+            (write_latency_us - self.write_latency_us_prev)
+                .checked_div(writes_succeeded)
+                .unwrap_or(0)
+                / 1000,
+        );
+        self.writes_prev = writes;
+        self.write_errors_prev = write_errors;
+        self.write_latency_us_prev = write_latency_us;
+
+        let view_lags = self.counters.view_lags();
+        let view_lag_us = self.counters.view_lag_us();
+        self.view_lag_avg_ms.push(
+            (view_lag_us - self.view_lag_us_prev)
+                .checked_div(view_lags - self.view_lags_prev)
+                .unwrap_or(0)
+                / 1000,
+        );
+        self.view_lags_prev = view_lags;
+        self.view_lag_us_prev = view_lag_us;
+
+        let rows_written = self.counters.rows_written();
+        let batches = self.counters.batches();
+        let batch_latency_us = self.counters.batch_latency_us();
+        self.rows_written
+            .push(rows_written - self.rows_written_prev);
+        self.batch_latency_avg_ms.push(
+            (batch_latency_us - self.batch_latency_us_prev)
+                .checked_div(batches - self.batches_prev)
+                .unwrap_or(0)
+                / 1000,
+        );
+        self.rows_written_prev = rows_written;
+        self.batches_prev = batches;
+        self.batch_latency_us_prev = batch_latency_us;
     }
 
-    fn trim_metrics(&mut self) {
-        if self.queries_num.len() > 100 {
-            self.queries_num.remove(0);
-        }
-        if self.queries_iter_num.len() > 100 {
-            self.queries_iter_num.remove(0);
-        }
-        if self.errors_num.len() > 100 {
-            self.errors_num.remove(0);
-        }
-        if self.errors_iter_num.len() > 100 {
-            self.errors_iter_num.remove(0);
-        }
-        if self.latency_avg_ms.len() > 100 {
-            self.latency_avg_ms.remove(0);
-        }
-        if self.latency_percentile_ms.len() > 100 {
-            self.latency_percentile_ms.remove(0);
+    /// Validated reads since the last reset, as valid, missing, stale and
+    /// corrupt counts.
+    pub fn validation_since_reset(&self) -> [u64; 4] {
+        let totals = [
+            self.validator.valid(),
+            self.validator.missing(),
+            self.validator.stale(),
+            self.validator.corrupt(),
+        ];
+        let baseline = self.baseline.validation;
+        std::array::from_fn(|i| totals[i] - baseline[i])
+    }
+
+    pub fn errors_since_reset(&self) -> Vec<ErrorCount> {
+        errors::counts_since(&self.errors.counts(), &self.baseline.errors)
+    }
+
+    pub fn failed_requests_since_reset(&self) -> u64 {
+        self.counters.reads().errors() + self.counters.writes().errors()
+            - self.baseline.failed_requests
+    }
+
+    pub fn deletes_since_reset(&self) -> u64 {
+        self.deletes_prev - self.baseline.deletes
+    }
+
+    pub fn bytes_sent_since_reset(&self) -> u64 {
+        self.bytes_sent_total - self.baseline.bytes_sent
+    }
+
+    pub fn bytes_received_since_reset(&self) -> u64 {
+        self.bytes_received_total - self.baseline.bytes_received
+    }
+
+    /// Drops the history behind the sparklines, charts and node series, and
+    /// starts the cumulative latency distributions and the totals the TUI
+    /// shows over, so they only show load from here on. The run summary and
+    /// the results file are unaffected.
+    pub fn reset_metrics(&mut self) {
+        for series in [
+            &mut self.writes,
+            &mut self.reads,
+            &mut self.write_errors,
+            &mut self.read_errors,
+            &mut self.latency_avg_ms,
+            &mut self.latency_percentile_ms,
+            &mut self.bytes_written,
+            &mut self.bytes_read,
+            &mut self.rows_per_read,
+            &mut self.read_pages,
+            &mut self.read_latency_avg_ms,
+            &mut self.write_latency_avg_ms,
+            &mut self.view_lag_avg_ms,
+            &mut self.lwt_applied,
+            &mut self.lwt_not_applied,
+            &mut self.lwt_timeouts,
+            &mut self.rows_written,
+            &mut self.batch_latency_avg_ms,
+        ] {
+            *series = Series::default();
         }
+        self.node_series.clear();
+        self.latency.reset();
+        self.baseline = Baseline {
+            latency_us: self.counters.reads().latency_us() + self.counters.writes().latency_us(),
+            failed_requests: self.counters.reads().errors() + self.counters.writes().errors(),
+            errors: self.errors.counts(),
+            validation: [
+                self.validator.valid(),
+                self.validator.missing(),
+                self.validator.stale(),
+                self.validator.corrupt(),
+            ],
+            deletes: self.deletes_prev,
+            bytes_sent: self.bytes_sent_total,
+            bytes_received: self.bytes_received_total,
+        };
     }
 
     #[allow(dead_code)]
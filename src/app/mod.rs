@@ -1,62 +1,185 @@
+mod charts;
+mod control;
+mod counters;
+mod errors;
 mod events;
+mod histogram;
+mod latency;
+mod listener;
 pub mod logging;
 mod metrics;
+mod nodes;
+mod otel;
 mod render;
+mod results;
+mod series;
 mod state;
 mod system;
 mod tabs;
 mod tasks;
+mod traces;
+mod validation;
 
-use crate::app::system::initialize_system;
+use crate::app::charts::ChartWindow;
+use crate::app::control::Controls;
+use crate::app::counters::Counters;
+use crate::app::errors::Errors;
+use crate::app::latency::{LatencySnapshots, LatencyView};
+use crate::app::metrics::Baseline;
+use crate::app::nodes::{NodeCount, NodeSeries, Nodes};
+use crate::app::series::Series;
+use crate::app::system::{initialize_networks, initialize_system};
+use crate::app::traces::Tracer;
+use crate::app::validation::Validator;
 use crate::db::models::{ReadPayload, WritePayload};
+use crate::db::topology::{self, Topology};
 use crate::Opt;
 use scylla::Session;
 use state::AppState;
+use std::collections::{BTreeMap, VecDeque};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use sysinfo::System;
+use sysinfo::{Networks, System};
 use tabs::SelectedTab;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
+use tracing::{warn, Level};
 
 #[derive(Clone)]
 pub struct App {
-    queries_num: Vec<u64>,
-    queries_iter_num: Vec<u64>,
-    errors_num: Vec<u64>,
-    errors_iter_num: Vec<u64>,
-    latency_avg_ms: Vec<u64>,
-    latency_percentile_ms: Vec<u64>,
-    queries_num_prev: u64,
-    queries_iter_num_prev: u64,
-    errors_num_prev: u64,
-    errors_iter_num_prev: u64,
-    read_logs: Vec<String>,
+    writes: Series,
+    reads: Series,
+    write_errors: Series,
+    read_errors: Series,
+    latency_avg_ms: Series,
+    latency_percentile_ms: Series,
+    bytes_written: Series,
+    bytes_read: Series,
+    rows_per_read: Series,
+    read_pages: Series,
+    read_latency_avg_ms: Series,
+    write_latency_avg_ms: Series,
+    view_lag_avg_ms: Series,
+    lwt_applied: Series,
+    lwt_not_applied: Series,
+    lwt_timeouts: Series,
+    rows_written: Series,
+    batch_latency_avg_ms: Series,
+    bytes_written_prev: u64,
+    bytes_read_prev: u64,
+    reads_prev: u64,
+    read_errors_prev: u64,
+    read_pages_prev: u64,
+    rows_read_prev: u64,
+    read_latency_us_prev: u64,
+    deletes_prev: u64,
+    writes_prev: u64,
+    write_errors_prev: u64,
+    write_latency_us_prev: u64,
+    view_lags_prev: u64,
+    view_lag_us_prev: u64,
+    lwt_applied_prev: u64,
+    lwt_not_applied_prev: u64,
+    lwt_timeouts_prev: u64,
+    rows_written_prev: u64,
+    batches_prev: u64,
+    batch_latency_us_prev: u64,
+    bytes_sent: Series,
+    bytes_received: Series,
+    bytes_sent_total: u64,
+    bytes_received_total: u64,
+    read_logs: VecDeque<String>,
     cpu_usage: f32,
     memory_usage: f32,
+    topology: Option<Topology>,
     selected_tab: SelectedTab,
     state: AppState,
     system: Arc<std::sync::Mutex<System>>,
+    networks: Arc<std::sync::Mutex<Networks>>,
+    counters: Arc<Counters>,
+    validator: Arc<Validator>,
+    errors: Arc<Errors>,
+    nodes: Arc<Nodes>,
+    nodes_prev: BTreeMap<SocketAddr, NodeCount>,
+    node_series: BTreeMap<SocketAddr, NodeSeries>,
+    tracer: Arc<Tracer>,
+    chart_window: ChartWindow,
+    latency: LatencySnapshots,
+    latency_view: LatencyView,
+    baseline: Baseline,
+    log_level: Level,
+    log_scroll: usize,
+    controls: Arc<Controls>,
+    show_help: bool,
 }
 
 impl App {
     pub fn new() -> Self {
         Self {
-            queries_num: vec![],
-            queries_iter_num: vec![],
-            errors_num: vec![],
-            errors_iter_num: vec![],
-            latency_avg_ms: vec![],
-            latency_percentile_ms: vec![],
-            queries_num_prev: 0,
-            queries_iter_num_prev: 0,
-            errors_num_prev: 0,
-            errors_iter_num_prev: 0,
-            read_logs: vec![],
+            writes: Series::default(),
+            reads: Series::default(),
+            write_errors: Series::default(),
+            read_errors: Series::default(),
+            latency_avg_ms: Series::default(),
+            latency_percentile_ms: Series::default(),
+            bytes_written: Series::default(),
+            bytes_read: Series::default(),
+            rows_per_read: Series::default(),
+            read_pages: Series::default(),
+            read_latency_avg_ms: Series::default(),
+            write_latency_avg_ms: Series::default(),
+            view_lag_avg_ms: Series::default(),
+            lwt_applied: Series::default(),
+            lwt_not_applied: Series::default(),
+            lwt_timeouts: Series::default(),
+            rows_written: Series::default(),
+            batch_latency_avg_ms: Series::default(),
+            bytes_written_prev: 0,
+            bytes_read_prev: 0,
+            reads_prev: 0,
+            read_errors_prev: 0,
+            read_pages_prev: 0,
+            rows_read_prev: 0,
+            read_latency_us_prev: 0,
+            deletes_prev: 0,
+            writes_prev: 0,
+            write_errors_prev: 0,
+            write_latency_us_prev: 0,
+            view_lags_prev: 0,
+            view_lag_us_prev: 0,
+            lwt_applied_prev: 0,
+            lwt_not_applied_prev: 0,
+            lwt_timeouts_prev: 0,
+            rows_written_prev: 0,
+            batches_prev: 0,
+            batch_latency_us_prev: 0,
+            bytes_sent: Series::default(),
+            bytes_received: Series::default(),
+            bytes_sent_total: 0,
+            bytes_received_total: 0,
+            read_logs: VecDeque::new(),
             cpu_usage: 0.0,
             memory_usage: 0.0,
+            topology: None,
             selected_tab: SelectedTab::Metrics,
             state: AppState::Running,
             system: initialize_system(),
+            networks: initialize_networks(),
+            counters: Arc::new(Counters::default()),
+            validator: Arc::new(Validator::default()),
+            errors: Arc::new(Errors::default()),
+            nodes: Arc::new(Nodes::default()),
+            nodes_prev: BTreeMap::new(),
+            node_series: BTreeMap::new(),
+            tracer: Arc::new(Tracer::default()),
+            chart_window: ChartWindow::default(),
+            latency: LatencySnapshots::default(),
+            latency_view: LatencyView::default(),
+            baseline: Baseline::default(),
+            log_level: Level::TRACE,
+            log_scroll: 0,
+            controls: Arc::new(Controls::default()),
+            show_help: false,
         }
     }
 
@@ -68,6 +191,22 @@ impl App {
         session: Arc<Session>,
         opt: &Opt,
     ) -> anyhow::Result<()> {
+        self.topology = match topology::fetch(&session, opt).await {
+            Ok(topology) => {
+                for warning in &topology.warnings {
+                    warn!("{}", warning);
+                }
+                Some(topology)
+            }
+            Err(e) => {
+                warn!("Failed to fetch cluster topology: {}", e);
+                None
+            }
+        };
+
+        self.tracer = Arc::new(Tracer::new(opt));
+        self.controls = Arc::new(Controls::new(opt));
+
         let (tx, rx) = mpsc::unbounded_channel();
         let cancellation_token = CancellationToken::new();
 
@@ -81,9 +220,20 @@ impl App {
         let write_task =
             self.spawn_write_task::<W>(session.clone(), opt.clone(), cancellation_token.clone());
 
-        let display_task = self.spawn_display_task(session.clone(), cancellation_token.clone(), rx);
+        let view_lag_task =
+            self.spawn_view_lag_task(session.clone(), opt.clone(), cancellation_token.clone());
+
+        let otel_task = self.spawn_otel_task(cancellation_token.clone());
+
+        let display_task = self.spawn_display_task(opt.clone(), cancellation_token.clone(), rx);
 
-        tokio::try_join!(read_task, write_task, display_task)?;
+        tokio::try_join!(
+            read_task,
+            write_task,
+            view_lag_task,
+            otel_task,
+            display_task
+        )?;
 
         Ok(())
     }
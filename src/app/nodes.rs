@@ -0,0 +1,103 @@
+use crate::app::errors::Operation;
+use crate::app::series::Series;
+use crate::app::App;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Attempts of one operation a coordinator served.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct NodeOperation {
+    pub attempts: u64,
+    pub errors: u64,
+    /// Total latency of successful attempts.
+    pub latency_us: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct NodeCount {
+    pub reads: NodeOperation,
+    pub writes: NodeOperation,
+}
+
+impl NodeCount {
+    fn operation(&mut self, operation: Operation) -> &mut NodeOperation {
+        match operation {
+            Operation::Read => &mut self.reads,
+            Operation::Write => &mut self.writes,
+        }
+    }
+}
+
+/// Attempts by coordinator node. Each page of a paged read is an attempt of
+/// its own, since the driver may send pages to different coordinators. The
+/// driver doesn't tell history listeners which shard's connection served an
+/// attempt, so they can't be broken down any further than by node.
+#[derive(Debug, Default)]
+pub struct Nodes {
+    counts: Mutex<BTreeMap<SocketAddr, NodeCount>>,
+}
+
+impl Nodes {
+    pub fn add_attempt(&self, node: SocketAddr, operation: Operation, latency: Duration) {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(node).or_default().operation(operation);
+        count.attempts += 1;
+        count.latency_us += latency.as_micros() as u64;
+    }
+
+    pub fn add_error(&self, node: SocketAddr, operation: Operation) {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(node).or_default().operation(operation);
+        count.attempts += 1;
+        count.errors += 1;
+    }
+
+    pub fn counts(&self) -> BTreeMap<SocketAddr, NodeCount> {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+/// Per second rates and average latencies of a coordinator, one sample per tick.
+#[derive(Debug, Clone, Default)]
+pub struct NodeSeries {
+    pub reads: Series,
+    pub writes: Series,
+    pub throughput: Series,
+    pub errors: Series,
+    pub read_latency_avg_ms: Series,
+    pub write_latency_avg_ms: Series,
+}
+
+impl App {
+    pub fn update_nodes(&mut self) {
+        let counts = self.nodes.counts();
+        for (node, count) in &counts {
+            let prev = self.nodes_prev.get(node).copied().unwrap_or_default();
+            let series = self.node_series.entry(*node).or_default();
+            let reads = count.reads.attempts - prev.reads.attempts;
+            let writes = count.writes.attempts - prev.writes.attempts;
+            let read_errors = count.reads.errors - prev.reads.errors;
+            let write_errors = count.writes.errors - prev.writes.errors;
+            series.reads.push(reads);
+            series.writes.push(writes);
+            series.throughput.push(reads + writes);
+            series.errors.push(read_errors + write_errors);
+            series.read_latency_avg_ms.push(
+                (count.reads.latency_us - prev.reads.latency_us)
+                    .checked_div(reads - read_errors)
+                    .unwrap_or(0)
+                    / 1000,
+            );
+            series.write_latency_avg_ms.push(
+                (count.writes.latency_us - prev.writes.latency_us)
+                    .checked_div(writes - write_errors)
+                    .unwrap_or(0)
+                    / 1000,
+            );
+        }
+        self.nodes_prev = counts;
+    }
+}
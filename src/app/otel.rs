@@ -0,0 +1,598 @@
+use crate::app::counters::Counters;
+use crate::app::errors::Operation;
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use serde_json::{json, Value};
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{debug, info, info_span, warn, Span, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The target of operation spans, the only spans exported over OTLP.
+pub const TARGET: &str = "skylar::otel";
+
+/// Spans buffered between exports beyond this are dropped, so a collector
+/// that can't keep up doesn't grow skylar's memory without bound.
+const MAX_BUFFERED_SPANS: usize = 8192;
+
+static EXPORTER: OnceCell<Arc<Exporter>> = OnceCell::new();
+
+/// OTLP exporter configuration, read from the standard `OTEL_*` environment
+/// variables. Only the http/json protocol is supported.
+#[derive(Debug)]
+struct Config {
+    protocol: Option<String>,
+    traces_endpoint: Option<String>,
+    metrics_endpoint: Option<String>,
+    headers: Vec<(String, String)>,
+    resource: Vec<(String, String)>,
+    sample_ratio: f64,
+    metric_interval: Duration,
+}
+
+impl Config {
+    fn from_env() -> Option<Self> {
+        if env::var("OTEL_SDK_DISABLED").is_ok_and(|disabled| disabled == "true") {
+            return None;
+        }
+        let endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok();
+        let signal_endpoint = |signal: &str, exporter: &str| {
+            if env::var(exporter).is_ok_and(|exporter| exporter == "none") {
+                return None;
+            }
+            env::var(format!(
+                "OTEL_EXPORTER_OTLP_{}_ENDPOINT",
+                signal.to_uppercase()
+            ))
+            .ok()
+            .or_else(|| {
+                endpoint
+                    .as_ref()
+                    .map(|endpoint| format!("{}/v1/{}", endpoint.trim_end_matches('/'), signal))
+            })
+        };
+        let traces_endpoint = signal_endpoint("traces", "OTEL_TRACES_EXPORTER");
+        let metrics_endpoint = signal_endpoint("metrics", "OTEL_METRICS_EXPORTER");
+        if traces_endpoint.is_none() && metrics_endpoint.is_none() {
+            return None;
+        }
+
+        let mut resource = vec![(
+            "service.name".to_string(),
+            env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "skylar".to_string()),
+        )];
+        resource.extend(key_values("OTEL_RESOURCE_ATTRIBUTES"));
+
+        let sample_ratio = sample_ratio(
+            env::var("OTEL_TRACES_SAMPLER").ok().as_deref(),
+            env::var("OTEL_TRACES_SAMPLER_ARG").ok().as_deref(),
+        );
+
+        let metric_interval = env::var("OTEL_METRIC_EXPORT_INTERVAL")
+            .ok()
+            .and_then(|interval| interval.parse().ok())
+            .filter(|interval| *interval > 0)
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_secs(60));
+
+        Some(Self {
+            protocol: env::var("OTEL_EXPORTER_OTLP_PROTOCOL").ok(),
+            traces_endpoint,
+            metrics_endpoint,
+            headers: key_values("OTEL_EXPORTER_OTLP_HEADERS"),
+            resource,
+            sample_ratio,
+            metric_interval,
+        })
+    }
+}
+
+/// The fraction of requests to trace, from `OTEL_TRACES_SAMPLER` and
+/// `OTEL_TRACES_SAMPLER_ARG`. Tracing every request of a load generator would
+/// swamp the collector, so unless a sampler says otherwise only a small
+/// fraction is traced.
+fn sample_ratio(sampler: Option<&str>, arg: Option<&str>) -> f64 {
+    match sampler {
+        Some("always_on" | "parentbased_always_on") => 1.0,
+        Some("always_off" | "parentbased_always_off") => 0.0,
+        _ => arg
+            .and_then(|ratio| ratio.parse::<f64>().ok())
+            .filter(|ratio| ratio.is_finite())
+            .unwrap_or(0.001)
+            .clamp(0.0, 1.0),
+    }
+}
+
+/// Parses a `key=value,key=value` list, as used by the headers and resource
+/// attributes variables.
+fn key_values(name: &str) -> Vec<(String, String)> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Buffers finished spans and sends them, and counter snapshots as metrics,
+/// to the collector.
+#[derive(Debug)]
+pub struct Exporter {
+    config: Config,
+    client: reqwest::Client,
+    spans: Mutex<Vec<Value>>,
+    started: SystemTime,
+    /// Whether the last export failed, so only the first of a run of
+    /// failures is logged as a warning.
+    failing: AtomicBool,
+}
+
+/// Creates the exporter when `OTEL_*` variables configure one, returning the
+/// layer that feeds it operation spans.
+pub fn layer() -> Option<OtlpLayer> {
+    let config = Config::from_env()?;
+    let exporter = Arc::new(Exporter::new(config));
+    EXPORTER.set(exporter.clone()).ok()?;
+    Some(OtlpLayer { exporter })
+}
+
+pub fn exporter() -> Option<&'static Arc<Exporter>> {
+    EXPORTER.get()
+}
+
+/// A span for a read or write request, or a disabled one when the request
+/// isn't sampled.
+pub fn span(operation: Operation, keyspace: &str) -> Span {
+    let sampled = exporter().is_some_and(|exporter| {
+        exporter.config.traces_endpoint.is_some()
+            && rand::thread_rng().gen_bool(exporter.config.sample_ratio)
+    });
+    if !sampled {
+        return Span::none();
+    }
+    info_span!(
+        target: TARGET,
+        "request",
+        db.system = "scylla",
+        db.namespace = keyspace,
+        db.operation.name = %operation,
+        db.response.returned_rows = tracing::field::Empty,
+        error = tracing::field::Empty,
+    )
+}
+
+impl Exporter {
+    fn new(config: Config) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            spans: Mutex::new(vec![]),
+            started: SystemTime::now(),
+            failing: AtomicBool::new(false),
+        }
+    }
+
+    /// Logs where spans and metrics go. The exporter is created with the
+    /// subscriber, before anything could be logged.
+    pub fn log_configuration(&self) {
+        if let Some(protocol) = self
+            .config
+            .protocol
+            .as_ref()
+            .filter(|protocol| *protocol != "http/json")
+        {
+            warn!(
+                "OTEL_EXPORTER_OTLP_PROTOCOL {} is not supported, exporting http/json",
+                protocol
+            );
+        }
+        if let Some(endpoint) = &self.config.traces_endpoint {
+            info!(
+                "Exporting {} of requests as OTLP spans to {}",
+                self.config.sample_ratio, endpoint
+            );
+        }
+        if let Some(endpoint) = &self.config.metrics_endpoint {
+            info!(
+                "Exporting OTLP metrics every {:?} to {}",
+                self.config.metric_interval, endpoint
+            );
+        }
+    }
+
+    pub fn metric_interval(&self) -> Duration {
+        self.config.metric_interval
+    }
+
+    fn push(&self, span: Value) {
+        let mut spans = self.spans.lock().unwrap();
+        if spans.len() < MAX_BUFFERED_SPANS {
+            spans.push(span);
+        }
+    }
+
+    pub async fn export_spans(&self) {
+        let Some(endpoint) = &self.config.traces_endpoint else {
+            return;
+        };
+        let spans = std::mem::take(&mut *self.spans.lock().unwrap());
+        if spans.is_empty() {
+            return;
+        }
+        let body = json!({
+            "resourceSpans": [{
+                "resource": self.resource(),
+                "scopeSpans": [{ "scope": scope(), "spans": spans }],
+            }],
+        });
+        self.post(endpoint, &body).await;
+    }
+
+    /// Sends cumulative request, row and page totals and latency
+    /// distributions by operation.
+    pub async fn export_metrics(&self, counters: &Counters) {
+        let Some(endpoint) = &self.config.metrics_endpoint else {
+            return;
+        };
+        let start = unix_nanos(self.started);
+        let now = unix_nanos(SystemTime::now());
+        let sum = |name: &str, unit: &str, value: fn(&Counters, Operation) -> u64| {
+            let data_points: Vec<Value> = [Operation::Read, Operation::Write]
+                .into_iter()
+                .map(|operation| {
+                    json!({
+                        "attributes": attributes(&[(
+                            "db.operation.name".to_string(),
+                            operation.to_string(),
+                        )]),
+                        "startTimeUnixNano": start,
+                        "timeUnixNano": now,
+                        "asInt": value(counters, operation).to_string(),
+                    })
+                })
+                .collect();
+            json!({
+                "name": name,
+                "unit": unit,
+                "sum": {
+                    "aggregationTemporality": 2,
+                    "isMonotonic": true,
+                    "dataPoints": data_points,
+                },
+            })
+        };
+        let metrics = vec![
+            histogram(counters, start.as_str(), now.as_str()),
+            sum("skylar.requests", "{request}", |counters, operation| {
+                counters.operation(operation).requests()
+            }),
+            sum(
+                "skylar.request.errors",
+                "{request}",
+                |counters, operation| counters.operation(operation).errors(),
+            ),
+            sum("skylar.rows", "{row}", |counters, operation| {
+                counters.operation(operation).rows()
+            }),
+            sum("skylar.pages", "{page}", |counters, operation| {
+                counters.operation(operation).pages()
+            }),
+            sum(
+                "skylar.bytes",
+                "By",
+                |counters, operation| match operation {
+                    Operation::Read => counters.bytes_read(),
+                    Operation::Write => counters.bytes_written(),
+                },
+            ),
+        ];
+        let body = json!({
+            "resourceMetrics": [{
+                "resource": self.resource(),
+                "scopeMetrics": [{ "scope": scope(), "metrics": metrics }],
+            }],
+        });
+        self.post(endpoint, &body).await;
+    }
+
+    fn resource(&self) -> Value {
+        json!({ "attributes": attributes(&self.config.resource) })
+    }
+
+    async fn post(&self, endpoint: &str, body: &Value) {
+        let mut request = self
+            .client
+            .post(endpoint)
+            .header("content-type", "application/json")
+            .body(body.to_string());
+        for (key, value) in &self.config.headers {
+            request = request.header(key, value);
+        }
+        let error = match request.send().await {
+            Ok(response) if !response.status().is_success() => response.status().to_string(),
+            Ok(_) => {
+                self.failing.store(false, Ordering::Relaxed);
+                return;
+            }
+            Err(e) => e.to_string(),
+        };
+        if self.failing.swap(true, Ordering::Relaxed) {
+            debug!("OTLP export to {} failed: {}", endpoint, error);
+        } else {
+            warn!("OTLP export to {} failed: {}", endpoint, error);
+        }
+    }
+}
+
+/// Request latency by operation as a cumulative explicit bucket histogram
+/// in seconds, with bounds at powers of two of microseconds.
+fn histogram(counters: &Counters, start: &str, now: &str) -> Value {
+    let data_points: Vec<Value> = [Operation::Read, Operation::Write]
+        .into_iter()
+        .map(|operation| {
+            let operation_counters = counters.operation(operation);
+            let snapshot = operation_counters.latency().snapshot();
+            let groups = snapshot.power_of_two_groups();
+            // The last group has no upper bound, latencies past the one before
+            // it fall into it.
+            let bounds: Vec<f64> = groups[..groups.len() - 1]
+                .iter()
+                .map(|(bound, _)| *bound as f64 / 1_000_000.0)
+                .collect();
+            let counts: Vec<String> = groups.iter().map(|(_, count)| count.to_string()).collect();
+            json!({
+                "attributes": attributes(&[(
+                    "db.operation.name".to_string(),
+                    operation.to_string(),
+                )]),
+                "startTimeUnixNano": start,
+                "timeUnixNano": now,
+                "count": snapshot.count().to_string(),
+                "sum": operation_counters.latency_us() as f64 / 1_000_000.0,
+                "max": snapshot.max_us() as f64 / 1_000_000.0,
+                "bucketCounts": counts,
+                "explicitBounds": bounds,
+            })
+        })
+        .collect();
+    json!({
+        "name": "db.client.operation.duration",
+        "unit": "s",
+        "histogram": {
+            "aggregationTemporality": 2,
+            "dataPoints": data_points,
+        },
+    })
+}
+
+fn scope() -> Value {
+    json!({ "name": "skylar", "version": env!("CARGO_PKG_VERSION") })
+}
+
+fn attributes(attributes: &[(String, String)]) -> Value {
+    attributes
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// A random hex id of `bytes` bytes, 16 for trace ids and 8 for span ids.
+fn random_id(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
+}
+
+/// A span in flight, kept in the span's extensions until it closes.
+struct SpanData {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    started: SystemTime,
+    attributes: Vec<Value>,
+    failed: bool,
+}
+
+impl Visit for SpanData {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.attributes
+            .push(json!({ "key": field.name(), "value": { "intValue": value.to_string() } }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.attributes
+            .push(json!({ "key": field.name(), "value": { "intValue": value.to_string() } }));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.attributes
+            .push(json!({ "key": field.name(), "value": { "boolValue": value } }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "error" {
+            self.failed = true;
+        }
+        self.attributes
+            .push(json!({ "key": field.name(), "value": { "stringValue": value } }));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Turns operation spans into OTLP spans, client spans of the request they
+/// time, nested under any operation span they were created in.
+pub struct OtlpLayer {
+    exporter: Arc<Exporter>,
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let parent = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanData>()
+                .map(|data| (data.trace_id.clone(), data.span_id.clone()))
+        });
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => (random_id(16), None),
+        };
+        let mut data = SpanData {
+            trace_id,
+            span_id: random_id(8),
+            parent_span_id,
+            started: SystemTime::now(),
+            attributes: vec![],
+            failed: false,
+        };
+        attrs.record(&mut data);
+        span.extensions_mut().insert(data);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                values.record(data);
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        self.exporter.push(json!({
+            "traceId": data.trace_id,
+            "spanId": data.span_id,
+            "parentSpanId": data.parent_span_id.unwrap_or_default(),
+            "name": span.name(),
+            // SPAN_KIND_CLIENT
+            "kind": 3,
+            "startTimeUnixNano": unix_nanos(data.started),
+            "endTimeUnixNano": unix_nanos(SystemTime::now()),
+            "attributes": data.attributes,
+            // STATUS_CODE_ERROR or STATUS_CODE_UNSET
+            "status": { "code": if data.failed { 2 } else { 0 } },
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Answers one request on the listener with 200 and returns its JSON body.
+    fn receive_one(listener: TcpListener) -> JoinHandle<Value> {
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("POST /v1/metrics "));
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let Some((name, value)) = line.trim_end().split_once(':') else {
+                    break;
+                };
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .unwrap();
+            serde_json::from_slice(&body).unwrap()
+        })
+    }
+
+    #[tokio::test]
+    async fn exports_latency_histogram_in_seconds() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/metrics", listener.local_addr().unwrap());
+        let received = receive_one(listener);
+        let exporter = Exporter::new(Config {
+            protocol: None,
+            traces_endpoint: None,
+            metrics_endpoint: Some(endpoint),
+            headers: vec![],
+            resource: vec![("service.name".to_string(), "skylar".to_string())],
+            sample_ratio: 0.0,
+            metric_interval: Duration::from_secs(60),
+        });
+        let counters = Counters::default();
+        counters.reads().add_request(1, Duration::from_micros(100));
+        counters.reads().add_request(1, Duration::from_micros(3000));
+
+        exporter.export_metrics(&counters).await;
+        let body = received.join().unwrap();
+
+        let metrics = body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"]
+            .as_array()
+            .unwrap();
+        let duration = metrics
+            .iter()
+            .find(|metric| metric["name"] == "db.client.operation.duration")
+            .unwrap();
+        assert_eq!(duration["unit"], "s");
+        let reads = &duration["histogram"]["dataPoints"][0];
+        assert_eq!(reads["attributes"][0]["value"]["stringValue"], "read");
+        assert_eq!(reads["count"], "2");
+        assert!((reads["sum"].as_f64().unwrap() - 0.0031).abs() < 1e-9);
+        let bounds = reads["explicitBounds"].as_array().unwrap();
+        let counts = reads["bucketCounts"].as_array().unwrap();
+        assert_eq!(counts.len(), bounds.len() + 1);
+        let count_under = |seconds: f64| {
+            let index = bounds
+                .iter()
+                .position(|bound| bound.as_f64() == Some(seconds))
+                .unwrap();
+            counts[index].clone()
+        };
+        assert_eq!(count_under(0.000128), "1");
+        assert_eq!(count_under(0.004096), "1");
+        let writes = &duration["histogram"]["dataPoints"][1];
+        assert_eq!(writes["count"], "0");
+    }
+
+    #[test]
+    fn sample_ratio_rejects_non_finite_args() {
+        assert_eq!(sample_ratio(None, Some("nan")), 0.001);
+        assert_eq!(sample_ratio(None, Some("inf")), 0.001);
+        assert_eq!(sample_ratio(None, Some("2")), 1.0);
+        assert_eq!(sample_ratio(Some("traceidratio"), Some("0.25")), 0.25);
+        assert_eq!(sample_ratio(Some("always_on"), Some("nan")), 1.0);
+    }
+}
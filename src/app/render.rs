@@ -1,10 +1,18 @@
+use crate::app::errors::{ErrorClass, Operation};
+use crate::app::logging;
+use crate::app::series::Series;
 use crate::app::tabs::SelectedTab;
 use crate::app::App;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Sparkline, Tabs};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{
+    Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Row, Sparkline, Table, Tabs,
+};
 use ratatui::Frame;
+use std::collections::BTreeMap;
 use strum::IntoEnumIterator;
+use tracing::Level;
 
 impl App {
     pub fn render(&self, frame: &mut Frame) {
@@ -17,15 +25,73 @@ impl App {
         self.render_tabs(chunks[0], frame);
         match self.selected_tab {
             SelectedTab::Metrics => self.render_metrics(frame, chunks[1]),
+            SelectedTab::Charts => self.render_charts(frame, chunks[1]),
+            SelectedTab::Latency => self.render_latency(frame, chunks[1]),
             SelectedTab::Samples => self.render_samples(frame, chunks[1]),
+            SelectedTab::Nodes => self.render_nodes(frame, chunks[1]),
+            SelectedTab::Errors => self.render_errors(frame, chunks[1]),
+            SelectedTab::Traces => self.render_traces(frame, chunks[1]),
+            SelectedTab::Logs => self.render_logs(frame, chunks[1]),
             SelectedTab::System => self.render_system(frame, chunks[1]),
+            SelectedTab::Topology => self.render_topology(frame, chunks[1]),
         }
+        if self.show_help {
+            self.render_help(frame);
+        }
+    }
+
+    /// Key bindings, drawn over the selected tab until any key is pressed.
+    fn render_help(&self, frame: &mut Frame) {
+        const BINDINGS: [(&str, &str); 15] = [
+            ("l / Right", "Next tab"),
+            ("h / Left", "Previous tab"),
+            ("p / Space", "Pause or resume load"),
+            ("+ / -", "Raise or lower the target rate"),
+            ("r / R", "Add or remove a read worker"),
+            ("w / W", "Add or remove a write worker"),
+            ("d", "Switch key distribution"),
+            ("x", "Reset metrics"),
+            ("z / Z", "CHARTS: zoom out or in"),
+            ("c", "LATENCY: interval or cumulative"),
+            ("f", "LOGS: filter by level"),
+            ("j / k", "LOGS: scroll down or up"),
+            ("g / G", "LOGS: oldest or newest"),
+            ("?", "Show this help"),
+            ("q / Esc", "Quit"),
+        ];
+        let area = frame.area();
+        let width = 50.min(area.width);
+        let height = (BINDINGS.len() as u16 + 2).min(area.height);
+        let area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        let rows = BINDINGS
+            .iter()
+            .map(|(keys, action)| Row::new([*keys, *action]));
+        let table = Table::new(rows, [Constraint::Length(12), Constraint::Min(0)]).block(
+            Block::default()
+                .title("Keys (any key to close)")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(table, area);
     }
 
     fn render_system(&self, frame: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .constraints(
+                [
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(25),
+                ]
+                .as_ref(),
+            )
             .split(area);
 
         let cpu_gauge = Gauge::default()
@@ -39,15 +105,51 @@ impl App {
             .gauge_style(Style::default().fg(Color::LightBlue))
             .percent(self.memory_usage as u16);
         frame.render_widget(memory_gauge, chunks[1]);
+
+        self.render_sparkline(
+            frame,
+            chunks[2],
+            &format!(
+                "Host NIC Sent, {} total",
+                format_bytes(self.bytes_sent_since_reset())
+            ),
+            "B/s",
+            &self.bytes_sent,
+            Color::Magenta,
+        );
+        self.render_sparkline(
+            frame,
+            chunks[3],
+            &format!(
+                "Host NIC Received, {} total",
+                format_bytes(self.bytes_received_since_reset())
+            ),
+            "B/s",
+            &self.bytes_received,
+            Color::LightMagenta,
+        );
     }
 
     fn render_tabs(&self, area: Rect, frame: &mut Frame) {
         let titles = SelectedTab::iter()
             .map(|tab| tab.to_string())
             .collect::<Vec<_>>();
+        let controls = &self.controls;
+        let status = format!(
+            "{}rate {} | {} readers | {} writers | {} | ? help",
+            if controls.paused() { "PAUSED | " } else { "" },
+            controls.rate_description(),
+            controls.readers(),
+            controls.writers(),
+            controls.distribution(),
+        );
         let tabs = Tabs::new(titles)
             .select(self.selected_tab as usize)
-            .block(Block::default().borders(Borders::NONE))
+            .block(
+                Block::default()
+                    .title(Line::from(status).right_aligned())
+                    .borders(Borders::NONE),
+            )
             .highlight_style(Style::default().fg(Color::LightBlue));
         frame.render_widget(tabs, area);
     }
@@ -57,12 +159,16 @@ impl App {
             .direction(Direction::Vertical)
             .constraints(
                 [
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(20),
-                    Constraint::Percentage(20),
+                    Constraint::Percentage(11),
+                    Constraint::Percentage(11),
+                    Constraint::Percentage(10),
                     Constraint::Percentage(10),
                     Constraint::Percentage(10),
+                    Constraint::Percentage(10),
+                    Constraint::Percentage(10),
+                    Constraint::Percentage(10),
+                    Constraint::Percentage(9),
+                    Constraint::Percentage(9),
                 ]
                 .as_ref(),
             )
@@ -84,57 +190,629 @@ impl App {
             &self.latency_percentile_ms,
             Color::LightBlue,
         );
+        let mut writes_title = self.workers_title(
+            "Writes",
+            self.counters.writers_live(),
+            self.controls.writers(),
+        );
+        if self.lwt_applied_prev + self.lwt_not_applied_prev + self.lwt_timeouts_prev > 0 {
+            writes_title.push_str(&format!(
+                ", LWT applied {} / not applied {} / contention timeouts {}",
+                self.lwt_applied.last().unwrap_or(&0),
+                self.lwt_not_applied.last().unwrap_or(&0),
+                self.lwt_timeouts.last().unwrap_or(&0)
+            ));
+        }
         self.render_sparkline(
             frame,
             chunks[2],
-            "Writes",
+            &writes_title,
             "ops/s",
-            &self.queries_num,
+            &self.writes,
             Color::Green,
         );
+        let [valid, missing, stale, corrupt] = self.validation_since_reset();
+        let mut reads_title = self.workers_title(
+            "Reads",
+            self.counters.readers_live(),
+            self.controls.readers(),
+        );
+        if valid + missing + stale + corrupt > 0 {
+            reads_title.push_str(&format!(
+                ", validated {} / missing {} / stale {} / corrupt {}",
+                valid, missing, stale, corrupt
+            ));
+        }
         self.render_sparkline(
             frame,
             chunks[3],
-            "Reads",
+            &reads_title,
             "ops/s",
-            &self.queries_iter_num,
+            &self.reads,
             Color::LightGreen,
         );
+        let write_throughput_title = if self.batches_prev > 0 {
+            format!(
+                "Write Throughput, {} rows/s in batches averaging {} ms",
+                self.rows_written.last().unwrap_or(&0),
+                self.batch_latency_avg_ms.last().unwrap_or(&0)
+            )
+        } else {
+            "Write Throughput".to_string()
+        };
         self.render_sparkline(
             frame,
             chunks[4],
+            &write_throughput_title,
+            "B/s",
+            &self.bytes_written,
+            Color::Cyan,
+        );
+        self.render_sparkline(
+            frame,
+            chunks[5],
+            "Read Throughput",
+            "B/s",
+            &self.bytes_read,
+            Color::LightCyan,
+        );
+        let rows_per_read_title = format!(
+            "Rows per Read, {} pages/s",
+            self.read_pages.last().unwrap_or(&0)
+        );
+        self.render_sparkline(
+            frame,
+            chunks[6],
+            &rows_per_read_title,
+            "rows",
+            &self.rows_per_read,
+            Color::Yellow,
+        );
+        let latency_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(if self.view_lags_prev > 0 {
+                vec![Constraint::Ratio(1, 3); 3]
+            } else {
+                vec![Constraint::Ratio(1, 2); 2]
+            })
+            .split(chunks[7]);
+        self.render_sparkline(
+            frame,
+            latency_chunks[0],
+            "Write Latency",
+            "ms",
+            &self.write_latency_avg_ms,
+            Color::Yellow,
+        );
+        let read_latency_title = if self.deletes_prev > 0 {
+            format!(
+                "Read Latency, {} tombstones written",
+                self.deletes_since_reset()
+            )
+        } else {
+            "Read Latency".to_string()
+        };
+        self.render_sparkline(
+            frame,
+            latency_chunks[1],
+            &read_latency_title,
+            "ms",
+            &self.read_latency_avg_ms,
+            Color::LightYellow,
+        );
+        if self.view_lags_prev > 0 {
+            self.render_sparkline(
+                frame,
+                latency_chunks[2],
+                "View Update Lag",
+                "ms",
+                &self.view_lag_avg_ms,
+                Color::Magenta,
+            );
+        }
+        self.render_sparkline(
+            frame,
+            chunks[8],
             "Write Errors",
             "ops/s",
-            &self.errors_num,
+            &self.write_errors,
             Color::Red,
         );
         self.render_sparkline(
             frame,
-            chunks[5],
+            chunks[9],
             "Read Errors",
             "ops/s",
-            &self.errors_iter_num,
+            &self.read_errors,
             Color::LightRed,
         );
     }
 
+    /// A sparkline title with how many of the configured workers are still
+    /// running, and how many have stopped.
+    fn workers_title(&self, title: &str, live: u64, configured: u64) -> String {
+        format!(
+            "{}, {} live / {} dead workers",
+            title,
+            live,
+            configured.saturating_sub(live)
+        )
+    }
+
     fn render_sparkline(
         &self,
         frame: &mut Frame,
         area: Rect,
         title: &str,
         unit: &str,
-        data: &[u64],
+        data: &Series,
         color: Color,
     ) {
         let title = format!("{} ({} {})", title, data.last().unwrap_or(&0), unit);
+        let data = data.recent(area.width.saturating_sub(2) as usize);
         let sparkline = Sparkline::default()
             .block(Block::default().title(title).borders(Borders::ALL))
-            .data(data)
+            .data(&data)
             .style(Style::default().fg(color));
         frame.render_widget(sparkline, area);
     }
 
+    fn render_topology(&self, frame: &mut Frame, area: Rect) {
+        let Some(topology) = &self.topology else {
+            let paragraph = Paragraph::new("Topology unavailable")
+                .block(Block::default().title("Topology").borders(Borders::ALL));
+            frame.render_widget(paragraph, area);
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(4 + topology.warnings.len() as u16),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let layout = match topology.initial_tablets {
+            Some(initial) => format!(
+                "tablets ({} initial), {}.{} has {} tablets",
+                initial, topology.keyspace, topology.table, topology.table_tablets
+            ),
+            None => "vnodes".to_string(),
+        };
+        let mut lines = vec![
+            Line::from(format!("Keyspace {} uses {}", topology.keyspace, layout)),
+            Line::from(format!("{} nodes", topology.nodes.len())),
+        ];
+        lines.extend(
+            topology
+                .warnings
+                .iter()
+                .map(|warning| Line::styled(warning.as_str(), Style::default().fg(Color::Red))),
+        );
+        let summary =
+            Paragraph::new(lines).block(Block::default().title("Layout").borders(Borders::ALL));
+        frame.render_widget(summary, chunks[0]);
+
+        let header = Row::new([
+            "Host ID",
+            "Address",
+            "Datacenter",
+            "Rack",
+            "Shards",
+            "Tokens",
+            "Tablet Replicas",
+        ])
+        .style(Style::default().fg(Color::LightBlue));
+        let rows = topology.nodes.iter().map(|node| {
+            Row::new([
+                node.host_id.to_string(),
+                node.address.clone(),
+                node.datacenter.clone(),
+                node.rack.clone(),
+                node.shards.map(|s| s.to_string()).unwrap_or_default(),
+                node.tokens.to_string(),
+                node.tablet_replicas.to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(36),
+                Constraint::Length(21),
+                Constraint::Length(14),
+                Constraint::Length(10),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(15),
+            ],
+        )
+        .header(header)
+        .block(Block::default().title("Nodes").borders(Borders::ALL));
+        frame.render_widget(table, chunks[1]);
+    }
+
+    /// Throughput and latency by coordinator, so a slow node or one taking
+    /// more than its share of requests stands out from the session averages.
+    fn render_nodes(&self, frame: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(self.node_series.len() as u16 + 3),
+                    Constraint::Min(0),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+
+        let total: u64 = self
+            .node_series
+            .values()
+            .map(|series| series.reads.last().unwrap_or(&0) + series.writes.last().unwrap_or(&0))
+            .sum();
+        let header = Row::new([
+            "Coordinator",
+            "DC",
+            "Rack",
+            "Shards",
+            "Reads/s",
+            "Writes/s",
+            "Share",
+            "Read ms",
+            "Write ms",
+            "Errors/s",
+        ])
+        .style(Style::default().fg(Color::LightBlue));
+        let rows = self.node_series.iter().map(|(node, series)| {
+            let layout = self.topology.as_ref().and_then(|topology| {
+                topology
+                    .nodes
+                    .iter()
+                    .find(|layout| layout.address == node.to_string())
+            });
+            let reads = series.reads.last().unwrap_or(&0);
+            let writes = series.writes.last().unwrap_or(&0);
+            Row::new([
+                node.to_string(),
+                layout.map(|l| l.datacenter.clone()).unwrap_or_default(),
+                layout.map(|l| l.rack.clone()).unwrap_or_default(),
+                layout
+                    .and_then(|l| l.shards)
+                    .map(|shards| shards.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                reads.to_string(),
+                writes.to_string(),
+                format!(
+                    "{}%",
+                    ((reads + writes) * 100).checked_div(total).unwrap_or(0)
+                ),
+                series.read_latency_avg_ms.last().unwrap_or(&0).to_string(),
+                series.write_latency_avg_ms.last().unwrap_or(&0).to_string(),
+                series.errors.last().unwrap_or(&0).to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(21),
+                Constraint::Length(12),
+                Constraint::Length(8),
+                Constraint::Length(7),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(6),
+                Constraint::Length(8),
+                Constraint::Length(9),
+                Constraint::Length(9),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title("Attempts by Coordinator, per node as the driver doesn't report shards")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[0]);
+
+        if self.node_series.is_empty() {
+            return;
+        }
+        let node_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Ratio(1, self.node_series.len() as u32);
+                self.node_series.len()
+            ])
+            .split(chunks[1]);
+        for ((node, series), area) in self.node_series.iter().zip(node_chunks.iter()) {
+            let series_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, 3); 3])
+                .split(*area);
+            self.render_sparkline(
+                frame,
+                series_chunks[0],
+                &format!("{} Throughput", node),
+                "ops/s",
+                &series.throughput,
+                Color::Green,
+            );
+            self.render_sparkline(
+                frame,
+                series_chunks[1],
+                &format!("{} Write Latency", node),
+                "ms",
+                &series.write_latency_avg_ms,
+                Color::Yellow,
+            );
+            self.render_sparkline(
+                frame,
+                series_chunks[2],
+                &format!("{} Read Latency", node),
+                "ms",
+                &series.read_latency_avg_ms,
+                Color::LightYellow,
+            );
+        }
+    }
+
+    fn render_errors(&self, frame: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(area);
+
+        // Errors are counted per attempt, so a request the retry policy
+        // retried before it failed or succeeded counts more than once.
+        let counts = self.errors_since_reset();
+        let failed_requests = self.failed_requests_since_reset();
+        let mut by_class: BTreeMap<ErrorClass, (u64, u64)> = BTreeMap::new();
+        let mut by_node: BTreeMap<String, (u64, u64, BTreeMap<ErrorClass, u64>)> = BTreeMap::new();
+        for count in &counts {
+            let (class_reads, class_writes) = by_class.entry(count.class).or_default();
+            let node = count
+                .node
+                .map(|node| node.to_string())
+                .unwrap_or_else(|| "no coordinator".to_string());
+            let (node_reads, node_writes, classes) = by_node.entry(node).or_default();
+            match count.operation {
+                Operation::Read => {
+                    *class_reads += count.count;
+                    *node_reads += count.count;
+                }
+                Operation::Write => {
+                    *class_writes += count.count;
+                    *node_writes += count.count;
+                }
+            }
+            *classes.entry(count.class).or_default() += count.count;
+        }
+
+        let header = Row::new(["Error", "Reads", "Writes", "Total"])
+            .style(Style::default().fg(Color::LightBlue));
+        let rows = by_class.iter().map(|(class, (reads, writes))| {
+            Row::new([
+                class.to_string(),
+                reads.to_string(),
+                writes.to_string(),
+                (reads + writes).to_string(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(20),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title(format!(
+                    "Failed Attempts by Class, {} failed requests",
+                    failed_requests
+                ))
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[0]);
+
+        let header = Row::new(["Coordinator", "Reads", "Writes", "Errors"])
+            .style(Style::default().fg(Color::LightBlue));
+        let rows = by_node.iter().map(|(node, (reads, writes, classes))| {
+            let classes = classes
+                .iter()
+                .map(|(class, count)| format!("{} {}", class, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Row::new([node.clone(), reads.to_string(), writes.to_string(), classes])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(21),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title("Failed Attempts by Coordinator")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[1]);
+    }
+
+    /// The slowest traced requests, and the events of the slowest of them.
+    fn render_traces(&self, frame: &mut Frame, area: Rect) {
+        if !self.tracer.enabled() {
+            let paragraph = Paragraph::new(
+                "Tracing is off. Enable it with --trace-probability or --trace-threshold-ms.",
+            )
+            .block(Block::default().title("Traces").borders(Borders::ALL));
+            frame.render_widget(paragraph, area);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(area);
+
+        let traces = self.tracer.traces();
+        let header = Row::new([
+            "Operation",
+            "Client ms",
+            "Server ms",
+            "Coordinator",
+            "Pages",
+            "Events",
+            "Request",
+        ])
+        .style(Style::default().fg(Color::LightBlue));
+        let rows = traces.iter().map(|trace| {
+            let server_us: i32 = trace
+                .sessions
+                .iter()
+                .filter_map(|session| session.duration_us)
+                .sum();
+            let first = trace.sessions.first();
+            Row::new([
+                trace.operation.to_string(),
+                format!("{:.1}", trace.latency_us as f64 / 1000.0),
+                format!("{:.1}", server_us as f64 / 1000.0),
+                first
+                    .and_then(|session| session.coordinator)
+                    .map(|coordinator| coordinator.to_string())
+                    .unwrap_or_default(),
+                trace.sessions.len().to_string(),
+                trace
+                    .sessions
+                    .iter()
+                    .map(|session| session.events.len())
+                    .sum::<usize>()
+                    .to_string(),
+                first
+                    .and_then(|session| session.parameters.get("query").cloned())
+                    .or_else(|| first.and_then(|session| session.request.clone()))
+                    .unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(16),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title("Slowest Traces")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[0]);
+
+        let header = Row::new(["Source", "Elapsed us", "Thread", "Activity"])
+            .style(Style::default().fg(Color::LightBlue));
+        let rows = traces
+            .first()
+            .into_iter()
+            .flat_map(|trace| &trace.sessions)
+            .flat_map(|session| &session.events)
+            .map(|event| {
+                Row::new([
+                    event
+                        .source
+                        .map(|source| source.to_string())
+                        .unwrap_or_default(),
+                    event
+                        .source_elapsed_us
+                        .map(|elapsed| elapsed.to_string())
+                        .unwrap_or_default(),
+                    event.thread.clone().unwrap_or_default(),
+                    event.activity.clone().unwrap_or_default(),
+                ])
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(16),
+                Constraint::Length(11),
+                Constraint::Length(12),
+                Constraint::Min(0),
+            ],
+        )
+        .header(header)
+        .block(
+            Block::default()
+                .title("Slowest Trace Events")
+                .borders(Borders::ALL),
+        );
+        frame.render_widget(table, chunks[1]);
+    }
+
+    fn render_logs(&self, frame: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let (lines, total) = logging::with_logs(|logs| {
+            let filtered = logs
+                .iter()
+                .rev()
+                .filter(|line| line.level <= self.log_level);
+            let total = filtered.clone().count();
+            let mut lines: Vec<Line> = filtered
+                .skip(self.log_scroll)
+                .take(height)
+                .map(|line| {
+                    let color = match line.level {
+                        Level::ERROR => Color::Red,
+                        Level::WARN => Color::Yellow,
+                        Level::INFO => Color::Green,
+                        Level::DEBUG => Color::LightBlue,
+                        Level::TRACE => Color::Gray,
+                    };
+                    Line::from(vec![
+                        Span::raw(format!("{} ", line.time.format("%H:%M:%S%.3f"))),
+                        Span::styled(format!("{:>5} ", line.level), Style::default().fg(color)),
+                        Span::styled(
+                            format!("{}: ", line.target),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                        Span::raw(line.message.clone()),
+                    ])
+                })
+                .collect();
+            lines.reverse();
+            (lines, total)
+        });
+        let title = if self.log_scroll > 0 {
+            format!(
+                "Logs, {} and above, {} of {} lines up (f: level, j/k: scroll, G: follow)",
+                self.log_level, self.log_scroll, total
+            )
+        } else {
+            format!(
+                "Logs, {} and above, {} lines (f: level, j/k: scroll, G: follow)",
+                self.log_level, total
+            )
+        };
+        let paragraph =
+            Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+        frame.render_widget(paragraph, area);
+    }
+
     fn render_samples(&self, frame: &mut Frame, area: Rect) {
         let items: Vec<ListItem> = self
             .read_logs
@@ -147,3 +825,14 @@ impl App {
         frame.render_widget(read_logs_list, area);
     }
 }
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
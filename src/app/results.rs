@@ -0,0 +1,67 @@
+use crate::app::errors::ErrorCount;
+use crate::app::nodes::NodeCount;
+use crate::app::App;
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+/// A summary of the run, written as JSON to --results-file.
+#[derive(Serialize)]
+struct Results<'a> {
+    payload: &'a str,
+    compression: &'a str,
+    reads: u64,
+    read_errors: u64,
+    rows_read: u64,
+    pages_read: u64,
+    bytes_read: u64,
+    writes: u64,
+    write_errors: u64,
+    rows_written: u64,
+    bytes_written: u64,
+    deletes: u64,
+    lwt_applied: u64,
+    lwt_not_applied: u64,
+    lwt_timeouts: u64,
+    /// Traffic on the client host's physical interfaces, CQL or not.
+    host_nic_bytes_sent: u64,
+    host_nic_bytes_received: u64,
+    /// Failed attempts, including ones the retry policy retried, so they
+    /// can outnumber read_errors and write_errors.
+    failed_attempts: Vec<ErrorCount>,
+    nodes: BTreeMap<SocketAddr, NodeCount>,
+}
+
+impl App {
+    pub fn export_results(&self, opt: &Opt) -> Result<()> {
+        let Some(path) = &opt.results_file else {
+            return Ok(());
+        };
+        let results = Results {
+            payload: &opt.payload,
+            compression: &opt.compression,
+            reads: self.counters.reads().requests(),
+            read_errors: self.counters.reads().errors(),
+            rows_read: self.counters.reads().rows(),
+            pages_read: self.counters.reads().pages(),
+            bytes_read: self.counters.bytes_read(),
+            writes: self.counters.writes().requests(),
+            write_errors: self.counters.writes().errors(),
+            rows_written: self.counters.rows_written(),
+            bytes_written: self.counters.bytes_written(),
+            deletes: self.counters.deletes(),
+            lwt_applied: self.counters.lwt_applied(),
+            lwt_not_applied: self.counters.lwt_not_applied(),
+            lwt_timeouts: self.counters.lwt_timeouts(),
+            host_nic_bytes_sent: self.bytes_sent_total,
+            host_nic_bytes_received: self.bytes_received_total,
+            failed_attempts: self.errors.counts(),
+            nodes: self.nodes.counts(),
+        };
+        let json = serde_json::to_string_pretty(&results)?;
+        std::fs::write(path, json)
+            .map_err(|e| anyhow!("Error writing results to {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How often the display task samples the counters, one sample per series
+/// each time.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Samples kept per series, a day's worth at one a second.
+const HISTORY: usize = 86_400;
+
+/// A bounded history of samples, the oldest dropped first once it's full.
+#[derive(Debug, Clone, Default)]
+pub struct Series {
+    samples: VecDeque<u64>,
+    /// Samples ever pushed, so the position of a sample in the run is known
+    /// after older ones have been dropped.
+    pushed: u64,
+}
+
+impl Series {
+    pub fn push(&mut self, sample: u64) {
+        if self.samples.len() == HISTORY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.pushed += 1;
+    }
+
+    pub fn last(&self) -> Option<&u64> {
+        self.samples.back()
+    }
+
+    /// The most recent `count` samples, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<u64> {
+        self.samples
+            .range(self.samples.len().saturating_sub(count)..)
+            .copied()
+            .collect()
+    }
+
+    /// The most recent `count` samples as (seconds into the run, value)
+    /// points, reduced to at most `width` points by keeping the peak of each
+    /// run of adjacent samples so spikes aren't averaged away.
+    pub fn points(&self, count: usize, width: usize) -> Vec<(f64, f64)> {
+        let count = count.min(self.samples.len());
+        let first = self.pushed - count as u64;
+        let bucket = count.div_ceil(width.max(1)).max(1);
+        let seconds = SAMPLE_INTERVAL.as_secs_f64();
+        self.samples
+            .range(self.samples.len() - count..)
+            .copied()
+            .collect::<Vec<_>>()
+            .chunks(bucket)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let x = (first + (index * bucket) as u64) as f64 * seconds;
+                (x, *chunk.iter().max().unwrap_or(&0) as f64)
+            })
+            .collect()
+    }
+
+    /// Seconds into the run of the most recent sample.
+    pub fn end(&self) -> f64 {
+        self.pushed.saturating_sub(1) as f64 * SAMPLE_INTERVAL.as_secs_f64()
+    }
+}
@@ -1,6 +1,14 @@
 use crate::app::App;
 use std::sync::{Arc, Mutex};
-use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, Networks, RefreshKind, System};
+
+/// Interface name prefixes left out of network traffic: loopback, which never
+/// leaves the host, and bridges, veth pairs and tunnels, whose traffic is also
+/// counted on the physical interface it goes out through.
+const VIRTUAL_INTERFACES: [&str; 14] = [
+    "lo", "docker", "br-", "veth", "virbr", "vnet", "cni", "flannel", "cali", "vxlan", "tun",
+    "tap", "wg", "podman",
+];
 
 pub fn initialize_system() -> Arc<Mutex<System>> {
     Arc::new(Mutex::new(System::new_with_specifics(
@@ -10,6 +18,10 @@ pub fn initialize_system() -> Arc<Mutex<System>> {
     )))
 }
 
+pub fn initialize_networks() -> Arc<Mutex<Networks>> {
+    Arc::new(Mutex::new(Networks::new_with_refreshed_list()))
+}
+
 impl App {
     pub fn update_system(&mut self) {
         let mut system = self.system.lock().unwrap();
@@ -18,4 +30,29 @@ impl App {
         self.cpu_usage = system.global_cpu_usage();
         self.memory_usage = system.used_memory() as f32 / system.total_memory() as f32 * 100.0;
     }
+
+    /// Traffic on the host's physical interfaces. The driver doesn't count the
+    /// bytes of the frames it sends, so this includes any other traffic on the
+    /// client machine, not just CQL.
+    pub fn update_networks(&mut self) {
+        let mut networks = self.networks.lock().unwrap();
+        networks.refresh();
+        let (sent, received) = networks
+            .list()
+            .iter()
+            .filter(|(name, _)| {
+                !VIRTUAL_INTERFACES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+            })
+            .map(|(_, data)| data)
+            .fold((0, 0), |(sent, received), data| {
+                (sent + data.transmitted(), received + data.received())
+            });
+
+        self.bytes_sent_total += sent;
+        self.bytes_received_total += received;
+        self.bytes_sent.push(sent);
+        self.bytes_received.push(received);
+    }
 }
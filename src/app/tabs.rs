@@ -5,10 +5,24 @@ pub enum SelectedTab {
     #[default]
     #[strum(to_string = "METRICS")]
     Metrics,
+    #[strum(to_string = "CHARTS")]
+    Charts,
+    #[strum(to_string = "LATENCY")]
+    Latency,
     #[strum(to_string = "SAMPLES")]
     Samples,
+    #[strum(to_string = "NODES")]
+    Nodes,
+    #[strum(to_string = "ERRORS")]
+    Errors,
+    #[strum(to_string = "TRACES")]
+    Traces,
+    #[strum(to_string = "LOGS")]
+    Logs,
     #[strum(to_string = "SYSTEM")]
     System,
+    #[strum(to_string = "TOPOLOGY")]
+    Topology,
 }
 
 impl SelectedTab {
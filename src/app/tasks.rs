@@ -1,17 +1,39 @@
+use crate::app::control::CONTROL_POLL_INTERVAL;
+use crate::app::counters::Counters;
+use crate::app::errors::{classify, Errors, Operation};
+use crate::app::listener::RequestListener;
+use crate::app::logging;
+use crate::app::otel;
+use crate::app::series::SAMPLE_INTERVAL;
 use crate::app::state::AppState;
 use crate::app::App;
-use crate::db::models::{ReadPayload, WritePayload};
+use crate::db::models::lwt;
+use crate::db::models::timeseries::{self, Device};
+use crate::db::models::{qualify, ReadPayload, WritePayload};
 use crate::Opt;
 use futures::StreamExt;
+use scylla::batch::{Batch, BatchType};
 use scylla::prepared_statement::PreparedStatement;
+use scylla::transport::errors::{DbError, QueryError, WriteType};
 use scylla::Session;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time;
 use tokio::time::Instant;
+use tokio_retry::Retry;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn, Instrument};
+
+/// How often buffered spans are sent to the OTLP collector.
+const OTEL_SPAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the display task checks for key presses between samples.
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a view lag probe waits for its row before giving up.
+const VIEW_LAG_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl App {
     pub fn spawn_read_task<W, R>(
@@ -25,31 +47,92 @@ impl App {
         W: WritePayload + scylla::serialize::row::SerializeRow + scylla::FromRow + std::fmt::Debug,
         R: ReadPayload + scylla::serialize::row::SerializeRow + scylla::FromRow + std::fmt::Debug,
     {
+        let counters = self.counters.clone();
+        let validator = self.validator.clone();
+        let errors = self.errors.clone();
+        let tracer = self.tracer.clone();
+        let controls = self.controls.clone();
+        let listener = Arc::new(RequestListener::new(
+            Operation::Read,
+            self.errors.clone(),
+            self.counters.clone(),
+            self.nodes.clone(),
+        ));
         tokio::spawn(async move {
-            for _ in 0..opt.readers {
+            let select_query = qualify(R::select_query(), &opt.keyspace, &opt.table());
+            let spawn_worker = |cancellation_token: CancellationToken| {
                 let session = session.clone();
-                let statement: PreparedStatement = session
-                    .prepare(R::select_query())
-                    .await
-                    .expect("Failed to prepare SELECT statement");
+                let select_query = select_query.clone();
+                let opt = opt.clone();
+                let listener = listener.clone();
+                let errors = errors.clone();
+                let tracer = tracer.clone();
                 let tx = tx.clone();
-                let distribution = opt.distribution.clone();
-                let cancellation_token = cancellation_token.clone();
+                let counters = counters.clone();
+                let validator = validator.clone();
+                let validate = opt.validate;
+                let controls = controls.clone();
                 tokio::spawn(async move {
+                    let _live = LiveWorker::new(counters.clone(), Operation::Read);
+                    let Some(mut statement) = prepare_with_retry(
+                        &session,
+                        &select_query,
+                        &opt,
+                        Operation::Read,
+                        &errors,
+                        &cancellation_token,
+                    )
+                    .await
+                    else {
+                        return;
+                    };
+                    statement.set_page_size(opt.page_size);
+                    statement.set_history_listener(listener);
+
                     let start_time = Instant::now();
                     loop {
+                        controls.wait_while_paused(&cancellation_token).await;
+                        if cancellation_token.is_cancelled() {
+                            break;
+                        }
+
                         let start = Instant::now();
-                        let statement = statement.clone();
-                        let payload = R::select_values(distribution.as_str());
-                        let mut rows_stream = session
-                            .execute_iter(statement, &payload)
-                            .await
-                            .expect("Failed to execute query")
-                            .into_typed::<W>();
-
-                        while let Some(next_row_res) = rows_stream.next().await {
+                        let payload = R::select_values(controls.distribution());
+                        let validation_key = payload.validation_key().filter(|_| validate);
+                        let trace = tracer.sample();
+                        let span = otel::span(Operation::Read, &opt.keyspace);
+                        let rows_stream = Retry::spawn(retry_strategy(&opt), || {
+                            let mut statement = statement.clone();
+                            statement.set_tracing(trace);
+                            session.execute_iter(statement, &payload)
+                        })
+                        .instrument(span.clone())
+                        .await;
+
+                        let mut rows = 0;
+                        let mut failed = false;
+                        let mut rows_stream = match rows_stream {
+                            Ok(rows_stream) => Some(rows_stream.into_typed::<W>()),
+                            Err(e) => {
+                                failed = true;
+                                span.record("error", e.to_string().as_str());
+                                error!("Error executing read: {}", e);
+                                None
+                            }
+                        };
+                        while let Some(next_row_res) = match rows_stream.as_mut() {
+                            Some(rows_stream) => rows_stream.next().await,
+                            None => None,
+                        } {
                             match next_row_res {
                                 Ok(payload) => {
+                                    rows += 1;
+                                    counters.add_bytes_read(payload.size());
+                                    if let (Some(key), Some((_, digest))) =
+                                        (validation_key, payload.validation_digest())
+                                    {
+                                        validator.check_read(key, Some(digest), start.into_std());
+                                    }
                                     debug!("{:?}", payload);
                                     if tx.send(format!("{:?}", payload)).is_err() {
                                         debug!("Failed to send row to display task");
@@ -57,10 +140,31 @@ impl App {
                                     }
                                 }
                                 Err(e) => {
+                                    failed = true;
+                                    span.record("error", e.to_string().as_str());
                                     error!("Error reading payload: {}", e);
                                 }
                             }
                         }
+                        let latency = start.elapsed();
+                        span.record("db.response.returned_rows", rows);
+                        drop(span);
+                        if failed {
+                            counters.reads().add_error();
+                        } else {
+                            counters.reads().add_request(rows, latency);
+                        }
+                        if let (true, false, Some(rows_stream)) = (trace, failed, &rows_stream) {
+                            tracer.record(
+                                &session,
+                                Operation::Read,
+                                rows_stream.get_tracing_ids().to_vec(),
+                                latency,
+                            );
+                        }
+                        if let (Some(key), 0, false) = (validation_key, rows, failed) {
+                            validator.check_read(key, None, start.into_std());
+                        }
 
                         if cancellation_token.is_cancelled() {
                             break;
@@ -69,9 +173,9 @@ impl App {
                         let elapsed = start_time.elapsed().as_secs_f64();
 
                         let pacing = Self::calculate_pacing(
-                            opt.readers as f64,
-                            opt.rate_min as f64,
-                            opt.rate_max as f64,
+                            controls.readers().max(1) as f64,
+                            controls.rate_min() as f64,
+                            controls.rate_max() as f64,
                             opt.rate_period as f64,
                             elapsed,
                         );
@@ -81,8 +185,9 @@ impl App {
                             time::sleep(pacing - elapsed).await;
                         }
                     }
-                });
-            }
+                })
+            };
+            run_workers(|| controls.readers(), &cancellation_token, spawn_worker).await;
         })
     }
 
@@ -95,25 +200,172 @@ impl App {
     where
         W: WritePayload + scylla::serialize::row::SerializeRow + scylla::FromRow + std::fmt::Debug,
     {
+        let counters = self.counters.clone();
+        let validator = self.validator.clone();
+        let errors = self.errors.clone();
+        let tracer = self.tracer.clone();
+        let controls = self.controls.clone();
+        let listener = Arc::new(RequestListener::new(
+            Operation::Write,
+            self.errors.clone(),
+            self.counters.clone(),
+            self.nodes.clone(),
+        ));
         tokio::spawn(async move {
-            for _ in 0..opt.writers {
+            let write_queries: Vec<String> = W::write_queries()
+                .into_iter()
+                .map(|query| qualify(query, &opt.keyspace, &opt.table()))
+                .collect();
+            let batch_size = opt.batch_size.max(1);
+            let batch_type = match opt.batch_type.as_str() {
+                "unlogged" => BatchType::Unlogged,
+                "counter" => BatchType::Counter,
+                _ => BatchType::Logged,
+            };
+            let group_by_partition = opt.batch_grouping == "partition";
+            let spawn_worker = |cancellation_token: CancellationToken| {
                 let session = session.clone();
-                let statement: PreparedStatement = session
-                    .prepare(W::insert_query())
-                    .await
-                    .expect("Failed to prepare INSERT statement");
-                let distribution = opt.distribution.clone();
-                let cancellation_token = cancellation_token.clone();
+                let write_queries = write_queries.clone();
+                let opt = opt.clone();
+                let errors = errors.clone();
+                let tracer = tracer.clone();
+                let counters = counters.clone();
+                let validator = validator.clone();
+                let validate = opt.validate;
+                let listener = listener.clone();
+                let controls = controls.clone();
                 tokio::spawn(async move {
+                    let _live = LiveWorker::new(counters.clone(), Operation::Write);
+                    let mut statements: Vec<PreparedStatement> = vec![];
+                    for query in &write_queries {
+                        let Some(mut statement) = prepare_with_retry(
+                            &session,
+                            query,
+                            &opt,
+                            Operation::Write,
+                            &errors,
+                            &cancellation_token,
+                        )
+                        .await
+                        else {
+                            return;
+                        };
+                        statement.set_history_listener(listener.clone());
+                        statements.push(statement);
+                    }
+
+                    // Built once and reused, rebuilt only when a payload that mixes
+                    // operations needs a different sequence of statements.
+                    let mut batch: Option<(Vec<usize>, Batch)> = None;
+
                     let start_time = Instant::now();
                     loop {
+                        controls.wait_while_paused(&cancellation_token).await;
+                        if cancellation_token.is_cancelled() {
+                            break;
+                        }
+
                         let start = Instant::now();
 
-                        let payload = W::insert_values(distribution.as_str());
-                        if let Err(e) = session.execute_unpaged(&statement, &payload).await {
-                            error!("Error inserting payload: {}", e);
+                        let first = W::insert_values(controls.distribution());
+                        let mut payloads = Vec::with_capacity(batch_size);
+                        for position in 1..batch_size {
+                            let mut payload = W::insert_values(controls.distribution());
+                            if group_by_partition {
+                                payload.colocate_with(&first, position);
+                            }
+                            payloads.push(payload);
+                        }
+                        payloads.insert(0, first);
+                        if validate {
+                            for payload in &payloads {
+                                if let Some((key, digest)) = payload.validation_digest() {
+                                    validator.record_write(key, digest);
+                                }
+                            }
                         }
 
+                        let trace = tracer.sample();
+                        let span = otel::span(Operation::Write, &opt.keyspace);
+                        let result = async {
+                            if payloads.len() == 1 {
+                                let statement = &statements[payloads[0].write_query_index()];
+                                if trace {
+                                    let mut statement = statement.clone();
+                                    statement.set_tracing(true);
+                                    session.execute_unpaged(&statement, &payloads[0]).await
+                                } else {
+                                    session.execute_unpaged(statement, &payloads[0]).await
+                                }
+                            } else {
+                                let indexes: Vec<usize> = payloads
+                                    .iter()
+                                    .map(|payload| payload.write_query_index())
+                                    .collect();
+                                if batch.as_ref().is_none_or(|(shape, _)| *shape != indexes) {
+                                    let mut new_batch = Batch::new(batch_type);
+                                    new_batch.set_history_listener(listener.clone());
+                                    for index in &indexes {
+                                        new_batch.append_statement(statements[*index].clone());
+                                    }
+                                    batch = Some((indexes, new_batch));
+                                }
+                                let (_, batch) = batch.as_mut().expect("batch was just built");
+                                batch.set_tracing(trace);
+                                let result = session.batch(batch, &payloads).await;
+                                counters.add_batch(start.elapsed());
+                                result
+                            }
+                        }
+                        .instrument(span.clone())
+                        .await;
+
+                        match result {
+                            Ok(result) => {
+                                let latency = start.elapsed();
+                                let rows = result.rows_num().unwrap_or(0) as u64;
+                                span.record("db.response.returned_rows", rows);
+                                counters.writes().add_request(rows, latency);
+                                if trace {
+                                    tracer.record(
+                                        &session,
+                                        Operation::Write,
+                                        result.tracing_id.into_iter().collect(),
+                                        latency,
+                                    );
+                                }
+                                for payload in &payloads {
+                                    payload.acknowledge(true);
+                                    payload.observe(&result);
+                                    if let (true, Some((key, digest))) =
+                                        (validate, payload.validation_digest())
+                                    {
+                                        validator.acknowledge_write(key, digest);
+                                    }
+                                    counters.add_bytes_written(payload.size());
+                                    if payload.is_delete() {
+                                        counters.add_delete();
+                                    }
+                                }
+                                counters.add_rows_written(payloads.len());
+                                if let Some(applied) = lwt::applied(&result) {
+                                    counters.add_lwt(applied);
+                                }
+                            }
+                            Err(e) => {
+                                span.record("error", e.to_string().as_str());
+                                counters.writes().add_error();
+                                for payload in &payloads {
+                                    payload.acknowledge(false);
+                                }
+                                if is_lwt_contention(&e) {
+                                    counters.add_lwt_timeout();
+                                }
+                                error!("Error inserting payload: {}", e);
+                            }
+                        }
+                        drop(span);
+
                         if cancellation_token.is_cancelled() {
                             break;
                         }
@@ -121,9 +373,9 @@ impl App {
                         let elapsed = start_time.elapsed().as_secs_f64();
 
                         let pacing = Self::calculate_pacing(
-                            opt.writers as f64,
-                            opt.rate_min as f64,
-                            opt.rate_max as f64,
+                            controls.writers().max(1) as f64,
+                            controls.rate_min() as f64,
+                            controls.rate_max() as f64,
                             opt.rate_period as f64,
                             elapsed,
                         );
@@ -133,14 +385,110 @@ impl App {
                             time::sleep(pacing - elapsed).await;
                         }
                     }
-                });
+                })
+            };
+            run_workers(|| controls.writers(), &cancellation_token, spawn_worker).await;
+        })
+    }
+
+    /// Sends buffered operation spans every second, and metrics at the
+    /// exporter's interval, to the OTLP collector until the run ends.
+    pub fn spawn_otel_task(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let counters = self.counters.clone();
+        tokio::spawn(async move {
+            let Some(exporter) = otel::exporter() else {
+                return;
+            };
+            exporter.log_configuration();
+            let mut spans = time::interval(OTEL_SPAN_INTERVAL);
+            let mut metrics = time::interval(exporter.metric_interval());
+            loop {
+                tokio::select! {
+                    _ = spans.tick() => exporter.export_spans().await,
+                    _ = metrics.tick() => exporter.export_metrics(&counters).await,
+                    _ = cancellation_token.cancelled() => break,
+                }
             }
+            exporter.export_spans().await;
+            exporter.export_metrics(&counters).await;
         })
     }
 
-    pub fn spawn_display_task(
+    /// Writes a probe row every second and polls each global index and view
+    /// until the row shows up through it, recording how long that took.
+    pub fn spawn_view_lag_task(
         &self,
         session: Arc<Session>,
+        opt: Opt,
+        cancellation_token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let counters = self.counters.clone();
+        tokio::spawn(async move {
+            let lagging_queries = timeseries::lagging_queries(&opt);
+            if lagging_queries.is_empty() {
+                return;
+            }
+            let insert_query = qualify(Device::insert_query(), &opt.keyspace, &opt.table());
+            let insert = match session.prepare(insert_query).await {
+                Ok(statement) => statement,
+                Err(e) => {
+                    error!("Error preparing view lag probe: {}", e);
+                    return;
+                }
+            };
+            let mut probes = vec![];
+            for (column, query) in lagging_queries {
+                let query = qualify(&query, &opt.keyspace, &opt.table());
+                match session.prepare(query).await {
+                    Ok(statement) => probes.push((column, statement)),
+                    Err(e) => {
+                        error!("Error preparing view lag probe: {}", e);
+                        return;
+                    }
+                }
+            }
+
+            while !cancellation_token.is_cancelled() {
+                let device = timeseries::probe_device();
+                if let Err(e) = session.execute_unpaged(&insert, &device).await {
+                    error!("Error writing view lag probe: {}", e);
+                    time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+                let acknowledged = Instant::now();
+                let deadline = acknowledged + VIEW_LAG_TIMEOUT;
+                for (column, statement) in &probes {
+                    let value = timeseries::sled_column(column, &device.sled_id);
+                    loop {
+                        match session.execute_unpaged(statement, (&value,)).await {
+                            Ok(result) if result.rows_num().unwrap_or(0) > 0 => {
+                                counters.add_view_lag(acknowledged.elapsed());
+                                break;
+                            }
+                            Ok(_) => {}
+                            Err(e) => debug!("Error reading view lag probe: {}", e),
+                        }
+                        if Instant::now() > deadline || cancellation_token.is_cancelled() {
+                            error!(
+                                "View lag probe on {} not visible after {:?}",
+                                column, VIEW_LAG_TIMEOUT
+                            );
+                            break;
+                        }
+                        time::sleep(Duration::from_millis(10)).await;
+                    }
+                }
+                time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+    }
+
+    pub fn spawn_display_task(
+        &self,
+        opt: Opt,
         cancellation_token: CancellationToken,
         mut rx: mpsc::UnboundedReceiver<String>,
     ) -> tokio::task::JoinHandle<()> {
@@ -148,42 +496,106 @@ impl App {
         let app = Arc::new(Mutex::new(app_data));
         tokio::spawn(async move {
             let mut terminal = ratatui::init();
+            logging::set_tui_active(true);
 
+            // Metrics are sampled every SAMPLE_INTERVAL, and keys handled as
+            // they come in between samples, redrawing after each.
+            let mut next_sample = Instant::now();
+            let mut redraw = true;
             loop {
-                let metrics = session.get_metrics();
-                {
+                if Instant::now() >= next_sample {
+                    next_sample += SAMPLE_INTERVAL;
                     let mut app = app.lock().await;
-                    app.update_metrics(&metrics);
+                    app.update_networks();
+                    app.update_metrics();
+                    app.update_nodes();
+                    app.update_latency();
                     app.update_system();
-                }
 
-                while let Ok(row) = rx.try_recv() {
-                    let mut app = app.lock().await;
-                    app.read_logs.push(row);
-                    if app.read_logs.len() > 100 {
-                        app.read_logs.remove(0);
+                    while let Ok(row) = rx.try_recv() {
+                        if app.read_logs.len() == 100 {
+                            app.read_logs.pop_front();
+                        }
+                        app.read_logs.push_back(row);
                     }
+                    redraw = true;
                 }
 
                 let mut app = app.lock().await;
-                if let Err(e) = terminal.draw(|frame| app.render(frame)) {
-                    error!("Error drawing frame: {}", e);
+                if redraw {
+                    if let Err(e) = terminal.draw(|frame| app.render(frame)) {
+                        error!("Error drawing frame: {}", e);
+                    }
                 }
 
-                if let Err(e) = app.handle_events() {
-                    error!("Error handling events: {}", e);
-                }
+                redraw = match app.handle_events() {
+                    Ok(handled) => handled,
+                    Err(e) => {
+                        error!("Error handling events: {}", e);
+                        false
+                    }
+                };
 
                 if app.state == AppState::Quitting || cancellation_token.is_cancelled() {
+                    cancellation_token.cancel();
                     debug!("AppState is Quitting or CancellationToken is cancelled, exiting display_task loop");
                     break;
                 }
+                drop(app);
 
-                time::sleep(Duration::from_millis(1000)).await;
+                if !redraw {
+                    time::sleep_until(next_sample.min(Instant::now() + KEY_POLL_INTERVAL)).await;
+                }
             }
 
             terminal.clear().expect("Failed to clear terminal");
             terminal.show_cursor().expect("Failed to show cursor");
+            ratatui::restore();
+            logging::set_tui_active(false);
+
+            let app = app.lock().await;
+            if opt.validate {
+                info!(
+                    "Read validation: valid={} missing={} stale={} corrupt={}",
+                    app.validator.valid(),
+                    app.validator.missing(),
+                    app.validator.stale(),
+                    app.validator.corrupt()
+                );
+            }
+            info!(
+                "Run summary: compression={} reads={} writes={} pages_read={} host_nic_bytes_sent={} host_nic_bytes_received={} deletes={}",
+                opt.compression,
+                app.counters.reads().requests(),
+                app.counters.writes().requests(),
+                app.counters.reads().pages(),
+                app.bytes_sent_total,
+                app.bytes_received_total,
+                app.deletes_prev
+            );
+            let counters = &app.counters;
+            if counters.lwt_applied() + counters.lwt_not_applied() + counters.lwt_timeouts() > 0 {
+                info!(
+                    "Conditional writes: applied={} not_applied={} contention_timeouts={}",
+                    counters.lwt_applied(),
+                    counters.lwt_not_applied(),
+                    counters.lwt_timeouts()
+                );
+            }
+            let failed_attempts: u64 = app.errors.counts().iter().map(|count| count.count).sum();
+            if failed_attempts > 0 {
+                warn!(
+                    "{} failed attempts, {} failed requests, see the ERRORS tab or --results-file for a breakdown",
+                    failed_attempts,
+                    counters.reads().errors() + counters.writes().errors()
+                );
+            }
+            if let Err(e) = app.export_results(&opt) {
+                error!("{}", e);
+            }
+            if let Err(e) = app.tracer.export(&opt) {
+                error!("{}", e);
+            }
         })
     }
 
@@ -223,3 +635,118 @@ impl App {
         }
     }
 }
+
+/// Counts a worker as live for as long as it runs, including when it panics.
+struct LiveWorker {
+    counters: Arc<Counters>,
+    operation: Operation,
+}
+
+impl LiveWorker {
+    fn new(counters: Arc<Counters>, operation: Operation) -> Self {
+        counters.worker_started(operation);
+        Self {
+            counters,
+            operation,
+        }
+    }
+}
+
+impl Drop for LiveWorker {
+    fn drop(&mut self) {
+        self.counters.worker_stopped(self.operation);
+    }
+}
+
+/// Keeps as many workers running as `target` asks for until the run is
+/// cancelled. Each worker gets its own token, so one that's removed finishes
+/// its in-flight request before it stops.
+async fn run_workers(
+    target: impl Fn() -> u64,
+    cancellation_token: &CancellationToken,
+    mut spawn: impl FnMut(CancellationToken) -> JoinHandle<()>,
+) {
+    let mut workers: Vec<(CancellationToken, JoinHandle<()>)> = vec![];
+    let mut stopping: Vec<JoinHandle<()>> = vec![];
+    loop {
+        let target = target() as usize;
+        while workers.len() < target {
+            let token = cancellation_token.child_token();
+            workers.push((token.clone(), spawn(token)));
+        }
+        if workers.len() > target {
+            for (token, worker) in workers.split_off(target) {
+                token.cancel();
+                stopping.push(worker);
+            }
+        }
+        stopping.retain(|worker| !worker.is_finished());
+
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            _ = time::sleep(CONTROL_POLL_INTERVAL) => {}
+        }
+    }
+
+    // Wait for in-flight requests to finish once the run is cancelled.
+    futures::future::join_all(
+        workers
+            .into_iter()
+            .map(|(_, worker)| worker)
+            .chain(stopping),
+    )
+    .await;
+}
+
+/// Delays between retries of a failed request, doubling from
+/// --retry-backoff-ms up to --retry-backoff-max-ms, --retries times.
+fn retry_strategy(opt: &Opt) -> impl Iterator<Item = Duration> {
+    let backoff = opt.retry_backoff_ms;
+    let max = opt.retry_backoff_max_ms;
+    (0..opt.retries).map(move |retry| {
+        Duration::from_millis(backoff.saturating_mul(1 << retry.min(32)).min(max))
+    })
+}
+
+/// Prepares a statement, retrying until it succeeds or the run is cancelled,
+/// so a node that's down when a worker starts doesn't take the worker with it.
+async fn prepare_with_retry(
+    session: &Session,
+    query: &str,
+    opt: &Opt,
+    operation: Operation,
+    errors: &Errors,
+    cancellation_token: &CancellationToken,
+) -> Option<PreparedStatement> {
+    loop {
+        let prepared = Retry::spawn(retry_strategy(opt), || async {
+            session
+                .prepare(query)
+                .await
+                .inspect_err(|e| errors.add(operation, classify(e), None))
+        })
+        .await;
+        match prepared {
+            Ok(statement) => return Some(statement),
+            Err(e) => error!("Error preparing statement: {}", e),
+        }
+        if cancellation_token.is_cancelled() {
+            return None;
+        }
+        time::sleep(Duration::from_millis(opt.retry_backoff_max_ms)).await;
+    }
+}
+
+/// Paxos rounds that time out are usually lost to contention from other writers.
+fn is_lwt_contention(error: &QueryError) -> bool {
+    matches!(
+        error,
+        QueryError::DbError(
+            DbError::WriteTimeout {
+                write_type: WriteType::Cas,
+                ..
+            },
+            _
+        )
+    )
+}
@@ -0,0 +1,218 @@
+use crate::app::errors::Operation;
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use scylla::tracing::TracingInfo;
+use scylla::Session;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::debug;
+use uuid::Uuid;
+
+/// A traced request, with the trace session the coordinator recorded for each
+/// page it fetched. Unpaged requests and batches have exactly one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Trace {
+    pub operation: Operation,
+    pub latency_us: u64,
+    pub sessions: Vec<TraceSession>,
+}
+
+/// A row of `system_traces.sessions` with its `system_traces.events`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceSession {
+    pub session_id: String,
+    pub coordinator: Option<IpAddr>,
+    pub duration_us: Option<i32>,
+    pub request: Option<String>,
+    pub parameters: BTreeMap<String, String>,
+    pub started_at_ms: Option<i64>,
+    pub events: Vec<TraceEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEvent {
+    pub source: Option<IpAddr>,
+    pub source_elapsed_us: Option<i32>,
+    pub activity: Option<String>,
+    pub thread: Option<String>,
+}
+
+impl TraceSession {
+    fn new(session_id: Uuid, info: TracingInfo) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            coordinator: info.coordinator,
+            duration_us: info.duration,
+            request: info.request,
+            parameters: info.parameters.unwrap_or_default().into_iter().collect(),
+            started_at_ms: info.started_at.map(|started_at| started_at.0),
+            events: info
+                .events
+                .into_iter()
+                .map(|event| TraceEvent {
+                    source: event.source,
+                    source_elapsed_us: event.source_elapsed,
+                    activity: event.activity,
+                    thread: event.thread,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decides which requests are traced and keeps the slowest of them. Tracing
+/// is enabled on a --trace-probability fraction of requests, or on every
+/// request when only --trace-threshold-ms is set, and only traces of requests
+/// slower than the threshold are fetched from `system_traces`.
+#[derive(Debug, Default)]
+pub struct Tracer {
+    probability: f64,
+    threshold: Option<Duration>,
+    kept: usize,
+    traces: Mutex<Vec<Trace>>,
+}
+
+impl Tracer {
+    pub fn new(opt: &Opt) -> Self {
+        let threshold = opt.trace_threshold_ms.map(Duration::from_millis);
+        let probability = if opt.trace_probability == 0.0 && threshold.is_some() {
+            1.0
+        } else {
+            opt.trace_probability
+        };
+        Self {
+            probability,
+            threshold,
+            kept: opt.traces_kept,
+            traces: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.probability > 0.0
+    }
+
+    /// Whether to enable tracing on the next request.
+    pub fn sample(&self) -> bool {
+        self.enabled() && rand::thread_rng().gen_bool(self.probability)
+    }
+
+    /// Fetches the trace sessions of a traced request in the background and
+    /// keeps it if it is among the slowest so far.
+    pub fn record(
+        self: &Arc<Self>,
+        session: &Arc<Session>,
+        operation: Operation,
+        session_ids: Vec<Uuid>,
+        latency: Duration,
+    ) {
+        if session_ids.is_empty() || !self.is_slow(latency) {
+            return;
+        }
+        let tracer = self.clone();
+        let session = session.clone();
+        tokio::spawn(async move {
+            let mut sessions = Vec::with_capacity(session_ids.len());
+            for session_id in session_ids {
+                match session.get_tracing_info(&session_id).await {
+                    Ok(info) => sessions.push(TraceSession::new(session_id, info)),
+                    Err(e) => {
+                        debug!("Error fetching trace {}: {}", session_id, e);
+                        return;
+                    }
+                }
+            }
+            tracer.keep(Trace {
+                operation,
+                latency_us: latency.as_micros() as u64,
+                sessions,
+            });
+        });
+    }
+
+    /// Whether a request is over the threshold and slower than the fastest
+    /// trace kept, so fetching its trace isn't wasted.
+    fn is_slow(&self, latency: Duration) -> bool {
+        if self.threshold.is_some_and(|threshold| latency < threshold) {
+            return false;
+        }
+        let traces = self.traces.lock().unwrap();
+        traces.len() < self.kept
+            || traces
+                .last()
+                .is_some_and(|fastest| latency.as_micros() as u64 > fastest.latency_us)
+    }
+
+    fn keep(&self, trace: Trace) {
+        let mut traces = self.traces.lock().unwrap();
+        let index = traces.partition_point(|kept| kept.latency_us >= trace.latency_us);
+        traces.insert(index, trace);
+        traces.truncate(self.kept);
+    }
+
+    /// The traces kept, slowest first.
+    pub fn traces(&self) -> Vec<Trace> {
+        self.traces.lock().unwrap().clone()
+    }
+
+    pub fn export(&self, opt: &Opt) -> Result<()> {
+        let Some(path) = &opt.traces_file else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.traces())?;
+        std::fs::write(path, json)
+            .map_err(|e| anyhow!("Error writing traces to {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(latency_us: u64) -> Trace {
+        Trace {
+            operation: Operation::Read,
+            latency_us,
+            sessions: vec![],
+        }
+    }
+
+    fn latencies(tracer: &Tracer) -> Vec<u64> {
+        tracer
+            .traces()
+            .iter()
+            .map(|trace| trace.latency_us)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_slowest_traces_slowest_first() {
+        let tracer = Tracer {
+            kept: 3,
+            ..Default::default()
+        };
+        for latency_us in [20, 50, 10, 40, 30] {
+            tracer.keep(trace(latency_us));
+        }
+        assert_eq!(latencies(&tracer), vec![50, 40, 30]);
+    }
+
+    #[test]
+    fn only_requests_slower_than_the_threshold_and_the_fastest_kept_are_slow() {
+        let tracer = Tracer {
+            threshold: Some(Duration::from_micros(15)),
+            kept: 2,
+            ..Default::default()
+        };
+        assert!(!tracer.is_slow(Duration::from_micros(10)));
+        assert!(tracer.is_slow(Duration::from_micros(20)));
+        tracer.keep(trace(20));
+        tracer.keep(trace(30));
+        assert!(!tracer.is_slow(Duration::from_micros(20)));
+        assert!(tracer.is_slow(Duration::from_micros(25)));
+    }
+}
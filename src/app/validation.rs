@@ -0,0 +1,265 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use uuid::Uuid;
+
+/// Writes per key whose digest and acknowledgement time are remembered, to
+/// find the latest write acknowledged before a read started.
+const HISTORY: usize = 4;
+
+/// Locks the writes are spread over by key, so readers and writers of
+/// different keys rarely wait on each other.
+const SHARDS: usize = 64;
+
+/// A write to a key: its sequence number among the key's writes, a digest of
+/// its values and when the cluster acknowledged it, if it has yet.
+struct Write {
+    sequence: u64,
+    digest: u64,
+    acknowledged: Option<Instant>,
+}
+
+/// The latest writes to a key, and a summary of the earlier ones.
+#[derive(Default)]
+struct KeyWrites {
+    recent: VecDeque<Write>,
+    /// A bloom filter of the digests of writes that fell out of `recent`, so
+    /// a read returning a value written earlier is told apart from one
+    /// returning a value that was never written while using a fixed amount of
+    /// memory per key. Once a key has had many writes it reports some
+    /// corrupt values as stale.
+    earlier: [u64; 4],
+    next_sequence: u64,
+}
+
+impl KeyWrites {
+    /// The two bits of `earlier` a digest sets.
+    fn earlier_bits(digest: u64) -> [usize; 2] {
+        let hash = digest.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        [(hash >> 56) as usize, (hash >> 48) as usize & 0xff]
+    }
+
+    fn remember_earlier(&mut self, digest: u64) {
+        for bit in Self::earlier_bits(digest) {
+            self.earlier[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn written_earlier(&self, digest: u64) -> bool {
+        Self::earlier_bits(digest)
+            .iter()
+            .all(|bit| self.earlier[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// Tracks a digest of every key's recent writes so reads can check they
+/// returned what was last acknowledged. Writes are recorded before they are
+/// sent, so a read that races a write and returns its newer value isn't
+/// mistaken for corruption.
+pub struct Validator {
+    shards: Vec<Mutex<HashMap<Uuid, KeyWrites>>>,
+    valid: AtomicU64,
+    missing: AtomicU64,
+    stale: AtomicU64,
+    corrupt: AtomicU64,
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Validator {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            valid: AtomicU64::default(),
+            missing: AtomicU64::default(),
+            stale: AtomicU64::default(),
+            corrupt: AtomicU64::default(),
+        }
+    }
+}
+
+impl Validator {
+    fn shard(&self, key: &Uuid) -> &Mutex<HashMap<Uuid, KeyWrites>> {
+        &self.shards[(key.as_u128() % SHARDS as u128) as usize]
+    }
+
+    /// Records a write about to be sent.
+    pub fn record_write(&self, key: Uuid, digest: u64) {
+        let mut writes = self.shard(&key).lock().unwrap();
+        let key_writes = writes.entry(key).or_default();
+        let sequence = key_writes.next_sequence;
+        key_writes.next_sequence += 1;
+        if key_writes.recent.len() == HISTORY {
+            if let Some(write) = key_writes.recent.pop_front() {
+                key_writes.remember_earlier(write.digest);
+            }
+        }
+        key_writes.recent.push_back(Write {
+            sequence,
+            digest,
+            acknowledged: None,
+        });
+    }
+
+    /// Marks the most recent write of these values to the key as acknowledged.
+    pub fn acknowledge_write(&self, key: Uuid, digest: u64) {
+        let mut writes = self.shard(&key).lock().unwrap();
+        if let Some(write) = writes.get_mut(&key).and_then(|key_writes| {
+            key_writes
+                .recent
+                .iter_mut()
+                .rev()
+                .find(|write| write.digest == digest && write.acknowledged.is_none())
+        }) {
+            write.acknowledged = Some(Instant::now());
+        }
+    }
+
+    /// Checks the value a read that started at `started` returned for a key,
+    /// `None` when it returned no row.
+    pub fn check_read(&self, key: Uuid, digest: Option<u64>, started: Instant) {
+        let writes = self.shard(&key).lock().unwrap();
+        let Some(key_writes) = writes.get(&key) else {
+            // Nothing was written to the key during this run, so there is
+            // nothing to compare against.
+            return;
+        };
+        let latest = key_writes.recent.iter().rev().find(|write| {
+            write
+                .acknowledged
+                .is_some_and(|acknowledged| acknowledged <= started)
+        });
+        let counter = match (digest, latest) {
+            (None, Some(_)) => &self.missing,
+            (Some(digest), Some(latest)) => {
+                match key_writes
+                    .recent
+                    .iter()
+                    .rev()
+                    .find(|write| write.digest == digest)
+                {
+                    Some(write) if write.sequence >= latest.sequence => &self.valid,
+                    Some(_) => &self.stale,
+                    // Writes that fell out of `recent` are all older than the
+                    // latest acknowledged one.
+                    None if key_writes.written_earlier(digest) => &self.stale,
+                    None => &self.corrupt,
+                }
+            }
+            // No recent write was acknowledged before the read started, so
+            // there is no latest value to compare against.
+            (_, None) => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn valid(&self) -> u64 {
+        self.valid.load(Ordering::Relaxed)
+    }
+
+    pub fn missing(&self) -> u64 {
+        self.missing.load(Ordering::Relaxed)
+    }
+
+    pub fn stale(&self) -> u64 {
+        self.stale.load(Ordering::Relaxed)
+    }
+
+    pub fn corrupt(&self) -> u64 {
+        self.corrupt.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acknowledged(validator: &Validator, key: Uuid, digests: &[u64]) {
+        for digest in digests {
+            validator.record_write(key, *digest);
+            validator.acknowledge_write(key, *digest);
+        }
+    }
+
+    fn counts(validator: &Validator) -> (u64, u64, u64, u64) {
+        (
+            validator.valid(),
+            validator.missing(),
+            validator.stale(),
+            validator.corrupt(),
+        )
+    }
+
+    #[test]
+    fn latest_acknowledged_value_is_valid() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        acknowledged(&validator, key, &[1, 2]);
+        validator.check_read(key, Some(2), Instant::now());
+        assert_eq!(counts(&validator), (1, 0, 0, 0));
+    }
+
+    #[test]
+    fn value_of_a_write_in_flight_is_valid() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        acknowledged(&validator, key, &[1]);
+        validator.record_write(key, 2);
+        validator.check_read(key, Some(2), Instant::now());
+        assert_eq!(counts(&validator), (1, 0, 0, 0));
+    }
+
+    #[test]
+    fn values_written_earlier_are_stale() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        acknowledged(&validator, key, &[1, 2, 3, 4, 5, 6]);
+        validator.check_read(key, Some(5), Instant::now());
+        // Older than the writes whose acknowledgement is remembered.
+        validator.check_read(key, Some(1), Instant::now());
+        assert_eq!(counts(&validator), (0, 0, 2, 0));
+    }
+
+    #[test]
+    fn values_never_written_are_corrupt() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        acknowledged(&validator, key, &[1, 2, 3, 4, 5, 6]);
+        validator.check_read(key, Some(7), Instant::now());
+        assert_eq!(counts(&validator), (0, 0, 0, 1));
+    }
+
+    #[test]
+    fn memory_per_key_stays_bounded() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        let digests: Vec<u64> = (0..1000).map(|i| crate::db::models::digest(&i)).collect();
+        acknowledged(&validator, key, &digests);
+        let writes = validator.shard(&key).lock().unwrap();
+        assert_eq!(writes[&key].recent.len(), HISTORY);
+        drop(writes);
+        validator.check_read(key, Some(digests[999]), Instant::now());
+        validator.check_read(key, Some(digests[10]), Instant::now());
+        assert_eq!(counts(&validator), (1, 0, 1, 0));
+    }
+
+    #[test]
+    fn acknowledged_row_read_back_empty_is_missing() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        acknowledged(&validator, key, &[1]);
+        validator.check_read(key, None, Instant::now());
+        assert_eq!(counts(&validator), (0, 1, 0, 0));
+    }
+
+    #[test]
+    fn reads_without_an_earlier_acknowledged_write_are_not_counted() {
+        let validator = Validator::default();
+        let key = Uuid::new_v4();
+        validator.check_read(key, Some(1), Instant::now());
+        let started = Instant::now();
+        acknowledged(&validator, key, &[1]);
+        validator.check_read(key, Some(1), started);
+        validator.record_write(Uuid::new_v4(), 1);
+        assert_eq!(counts(&validator), (0, 0, 0, 0));
+    }
+}
@@ -1,34 +1,201 @@
 use std::time::Duration;
 
+use crate::db::credentials;
 use crate::db::models::cache::DDL_CACHE;
-use crate::db::models::timeseries::DDL_TIMESERIES;
+use crate::db::models::collections::DDL_COLLECTIONS;
+use crate::db::models::counter::DDL_COUNTER;
+use crate::db::models::keyvalue::{ValueSizes, DDL_KEY_VALUE};
+use crate::db::models::lwt::DDL_LWT;
+use crate::db::models::qualify;
+use crate::db::models::timeseries::{self, DDL_TIMESERIES, INDEXABLE_COLUMNS};
+use crate::db::models::widerow::DDL_WIDE_ROW;
 use crate::Opt;
 use anyhow::{anyhow, Result};
 use scylla::load_balancing::DefaultPolicy;
-use scylla::statement::Consistency;
-use scylla::transport::ExecutionProfile;
+use scylla::statement::{Consistency, SerialConsistency};
+use scylla::transport::{Compression, ExecutionProfile};
 use scylla::{Session, SessionBuilder};
 use tokio_retry::{strategy::ExponentialBackoff, Retry};
-use tracing::debug;
+use tracing::{debug, info};
 
 pub async fn builder(migrate: bool, opt: &Opt) -> Result<Session> {
     let host = opt.host.clone();
+    let table = opt.table();
 
-    let consistency = match opt.consistency_level.to_uppercase().as_str() {
-        "ONE" => Consistency::One,
-        "TWO" => Consistency::Two,
-        "THREE" => Consistency::Three,
-        "QUORUM" => Consistency::Quorum,
-        "ALL" => Consistency::All,
-        "LOCAL_QUORUM" => Consistency::LocalQuorum,
-        "EACH_QUORUM" => Consistency::EachQuorum,
-        "SERIAL" => Consistency::Serial,
-        "LOCAL_SERIAL" => Consistency::LocalSerial,
-        "LOCAL_ONE" => Consistency::LocalOne,
-        _ => Consistency::LocalQuorum,
+    if !matches!(opt.value_type.as_str(), "blob" | "text") {
+        return Err(anyhow!("Unsupported value type: {}", opt.value_type));
+    }
+    ValueSizes::parse(&opt.value_size)?;
+    if !matches!(
+        opt.read_shape.as_str(),
+        "latest" | "window" | "reverse" | "scan"
+    ) {
+        return Err(anyhow!("Unsupported read shape: {}", opt.read_shape));
+    }
+    if !matches!(
+        opt.lwt_operation.as_str(),
+        "insert" | "update" | "delete" | "mixed"
+    ) {
+        return Err(anyhow!("Unsupported LWT operation: {}", opt.lwt_operation));
+    }
+    if opt.batch_size > 1 {
+        let counter_payload = opt.payload == "counter";
+        match opt.batch_type.as_str() {
+            "counter" if counter_payload => {}
+            "logged" | "unlogged" if !counter_payload => {}
+            "logged" | "unlogged" | "counter" => {
+                return Err(anyhow!(
+                    "Batch type {} can't be used with the {} payload",
+                    opt.batch_type,
+                    opt.payload
+                ))
+            }
+            _ => return Err(anyhow!("Unsupported batch type: {}", opt.batch_type)),
+        }
+    }
+    if !(0.0..=1.0).contains(&opt.delete_ratio) {
+        return Err(anyhow!(
+            "Delete ratio must be between 0 and 1: {}",
+            opt.delete_ratio
+        ));
+    }
+    if !(0.0..=1.0).contains(&opt.trace_probability) {
+        return Err(anyhow!(
+            "Trace probability must be between 0 and 1: {}",
+            opt.trace_probability
+        ));
+    }
+    if !matches!(opt.delete_operation.as_str(), "row" | "range" | "partition") {
+        return Err(anyhow!(
+            "Unsupported delete operation: {}",
+            opt.delete_operation
+        ));
+    }
+    if !matches!(
+        opt.collection_operation.as_str(),
+        "insert" | "append" | "update" | "mixed"
+    ) {
+        return Err(anyhow!(
+            "Unsupported collection operation: {}",
+            opt.collection_operation
+        ));
+    }
+    let indexed_columns = opt
+        .secondary_index
+        .iter()
+        .chain(&opt.local_index)
+        .chain(&opt.materialized_view);
+    for column in indexed_columns {
+        if opt.payload != "timeseries" {
+            return Err(anyhow!(
+                "Indexes and materialized views are only supported by the timeseries payload"
+            ));
+        }
+        if !INDEXABLE_COLUMNS.contains(&column.as_str()) {
+            return Err(anyhow!(
+                "Unsupported indexed column: {}, expected one of {}",
+                column,
+                INDEXABLE_COLUMNS.join(", ")
+            ));
+        }
+    }
+    if !matches!(
+        opt.read_path.as_str(),
+        "base" | "index" | "local-index" | "view"
+    ) {
+        return Err(anyhow!("Unsupported read path: {}", opt.read_path));
+    }
+    if opt.read_path != "base" && opt.read_column().is_none() {
+        return Err(anyhow!(
+            "Read path {} needs a column to read through, see --secondary-index, --local-index and --materialized-view",
+            opt.read_path
+        ));
+    }
+    if opt.validate && opt.payload != "keyvalue" {
+        return Err(anyhow!(
+            "Read validation is only supported by the keyvalue payload"
+        ));
+    }
+    if (opt.verify || opt.verify_only) && opt.payload != "keyvalue" {
+        return Err(anyhow!(
+            "Lost write verification is only supported by the keyvalue payload"
+        ));
+    }
+    // Keys that expire before they are re-read would be reported as lost writes.
+    if (opt.verify || opt.verify_only) && opt.default_ttl.is_some_and(|ttl| ttl > 0) {
+        return Err(anyhow!(
+            "Lost write verification can't tell expired keys from lost writes, drop --default-ttl"
+        ));
+    }
+    if !matches!(opt.batch_grouping.as_str(), "partition" | "spread") {
+        return Err(anyhow!(
+            "Unsupported batch grouping: {}",
+            opt.batch_grouping
+        ));
+    }
+    if opt.batch_size > 1 {
+        // Conditional batches must stay within one partition, and lwt rows
+        // are keyed by partition key alone, so any batch of them either
+        // spans partitions or rewrites the same row.
+        if opt.payload == "lwt" {
+            return Err(anyhow!("The lwt payload can't be batched"));
+        }
+        // Grouping by partition only spreads writes over distinct rows when
+        // the table has a clustering key to vary.
+        let clustered = matches!(opt.payload.as_str(), "timeseries" | "widerow");
+        if opt.batch_grouping == "partition" && !clustered {
+            return Err(anyhow!(
+                "Batch grouping partition would write every statement to the same row of the {} payload, use --batch-grouping spread",
+                opt.payload
+            ));
+        }
+    }
+    if opt.rows_per_partition == 0 {
+        return Err(anyhow!("Rows per partition must be positive"));
+    }
+    if opt.read_rows == 0 {
+        return Err(anyhow!("Read rows must be positive"));
+    }
+    if i32::try_from(opt.read_rows).is_err() {
+        return Err(anyhow!(
+            "Read rows must be at most {}: {}",
+            i32::MAX,
+            opt.read_rows
+        ));
+    }
+    if opt.page_size <= 0 {
+        return Err(anyhow!("Page size must be positive: {}", opt.page_size));
+    }
+
+    for name in [opt.keyspace.as_str(), table.as_str()] {
+        if !is_valid_identifier(name) {
+            return Err(anyhow!("Invalid keyspace or table name: {}", name));
+        }
+    }
+
+    let consistency = parse_consistency(&opt.consistency_level);
+
+    // A serial level is only valid for the Paxos phase of a lightweight
+    // transaction, so pair it with the matching quorum for everything else.
+    let (consistency, serial_consistency) = match consistency {
+        Consistency::Serial => (Consistency::Quorum, SerialConsistency::Serial),
+        Consistency::LocalSerial => (Consistency::LocalQuorum, SerialConsistency::LocalSerial),
+        consistency => (consistency, SerialConsistency::LocalSerial),
+    };
+
+    let compression = match opt.compression.to_lowercase().as_str() {
+        "lz4" => Some(Compression::Lz4),
+        "snappy" => Some(Compression::Snappy),
+        "none" => None,
+        _ => return Err(anyhow!("Unsupported compression: {}", opt.compression)),
     };
 
-    debug!("Connecting to ScyllaDB at: {}  CL: {}", host, consistency);
+    debug!(
+        "Connecting to ScyllaDB at: {}  CL: {}  Serial CL: {}  Compression: {:?}",
+        host, consistency, serial_consistency, compression
+    );
+
+    let credentials = credentials::resolve(opt)?;
 
     let strategy = ExponentialBackoff::from_millis(500).max_delay(Duration::from_secs(20));
 
@@ -44,6 +211,7 @@ pub async fn builder(migrate: bool, opt: &Opt) -> Result<Session> {
         let profile = ExecutionProfile::builder()
             .load_balancing_policy(default_policy)
             .consistency(consistency)
+            .serial_consistency(Some(serial_consistency))
             .build();
 
         let handle = profile.into_handle();
@@ -51,7 +219,11 @@ pub async fn builder(migrate: bool, opt: &Opt) -> Result<Session> {
         SessionBuilder::new()
             .known_node(&host)
             .default_execution_profile_handle(handle)
-            .user(opt.username.clone(), opt.password.clone())
+            .compression(compression)
+            .user(
+                credentials.username.clone(),
+                credentials.password.expose().to_string(),
+            )
             .build()
             .await
     })
@@ -62,28 +234,257 @@ pub async fn builder(migrate: bool, opt: &Opt) -> Result<Session> {
         let tablets_enabled = if opt.tablets > 0 { "true" } else { "false" };
         let tablets = opt.tablets.to_string();
         let replication_factor = opt.replication_factor.to_string();
-        let schema_query = match opt.payload.as_str() {
+        let table_options = table_options(opt)?;
+        let mut schema_query = match opt.payload.as_str() {
             "timeseries" => DDL_TIMESERIES,
             "cache" => DDL_CACHE,
+            "keyvalue" => DDL_KEY_VALUE,
+            "widerow" => DDL_WIDE_ROW,
+            "lwt" => DDL_LWT,
+            "counter" => DDL_COUNTER,
+            "collections" => DDL_COLLECTIONS,
             _ => panic!("Unsupported payload type"),
         }
-        .trim()
-        .replace('\n', " ")
         .replace("<RF>", &replication_factor)
         .replace("<TABLETS>", &tablets)
-        .replace("<TABLETS_ENABLED>", tablets_enabled);
-
-        for q in schema_query.split(';') {
-            let query = q.to_owned() + ";";
-            if !query.starts_with("--") && query.len() > 1 {
-                debug!("Running Migration {}", query);
-                session
-                    .query_unpaged(query, &[])
-                    .await
-                    .map_err(|e| anyhow!("Error executing migration query: {}", e))?;
+        .replace("<TABLETS_ENABLED>", tablets_enabled)
+        .replace("<VALUE_TYPE>", &opt.value_type)
+        .replace(
+            "<TABLE_OPTIONS>",
+            &table_options
+                .as_ref()
+                .map(|options| format!(" WITH {}", options))
+                .unwrap_or_default(),
+        )
+        .replace(
+            "<AND_TABLE_OPTIONS>",
+            &table_options
+                .as_ref()
+                .map(|options| format!(" AND {}", options))
+                .unwrap_or_default(),
+        );
+
+        // CREATE TABLE IF NOT EXISTS ignores options for an existing table,
+        // so apply them again to make re-runs against the same table honour them.
+        if let Some(options) = &table_options {
+            schema_query.push_str(&format!(";ALTER TABLE <KEYSPACE>.<TABLE> WITH {}", options));
+        }
+        if opt.payload == "timeseries" {
+            let indexes = timeseries::index_ddl(opt);
+            if !indexes.is_empty() {
+                schema_query.push_str(&format!(";{}", indexes));
             }
         }
+        let schema_query = qualify(&schema_query, &opt.keyspace, &table);
+
+        for query in split_statements(&schema_query) {
+            debug!("Running Migration {}", query);
+            session
+                .query_unpaged(query, &[])
+                .await
+                .map_err(|e| anyhow!("Error executing migration query: {}", e))?;
+        }
+
+        await_schema_agreement(&session).await?;
     }
 
     Ok(session)
 }
+
+/// Parses a consistency level name, defaulting to LOCAL_QUORUM.
+pub fn parse_consistency(level: &str) -> Consistency {
+    match level.to_uppercase().as_str() {
+        "ONE" => Consistency::One,
+        "TWO" => Consistency::Two,
+        "THREE" => Consistency::Three,
+        "QUORUM" => Consistency::Quorum,
+        "ALL" => Consistency::All,
+        "LOCAL_QUORUM" => Consistency::LocalQuorum,
+        "EACH_QUORUM" => Consistency::EachQuorum,
+        "SERIAL" => Consistency::Serial,
+        "LOCAL_SERIAL" => Consistency::LocalSerial,
+        "LOCAL_ONE" => Consistency::LocalOne,
+        _ => Consistency::LocalQuorum,
+    }
+}
+
+/// Drops the keyspace or truncates the table at the end of a run, when asked to.
+pub async fn teardown(session: &Session, opt: &Opt) -> Result<()> {
+    let query = if opt.drop_keyspace {
+        format!("DROP KEYSPACE IF EXISTS {}", opt.keyspace)
+    } else if opt.truncate {
+        format!("TRUNCATE TABLE {}.{}", opt.keyspace, opt.table())
+    } else {
+        return Ok(());
+    };
+
+    info!("Running teardown {}", query);
+    session
+        .query_unpaged(query, &[])
+        .await
+        .map_err(|e| anyhow!("Error executing teardown query: {}", e))?;
+
+    await_schema_agreement(session).await
+}
+
+async fn await_schema_agreement(session: &Session) -> Result<()> {
+    let version = session
+        .await_schema_agreement()
+        .await
+        .map_err(|e| anyhow!("Error awaiting schema agreement: {}", e))?;
+    debug!("Schema agreement reached on version {}", version);
+    Ok(())
+}
+
+/// Splits a migration into statements on `;`, ignoring semicolons that appear
+/// inside string literals, quoted identifiers and `--`, `//` or `/* */` comments.
+/// Comments are dropped and empty statements are skipped.
+fn split_statements(ddl: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut chars = ddl.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                current.push(c);
+                while let Some(q) = chars.next() {
+                    current.push(q);
+                    if q == c {
+                        // A doubled quote is an escaped quote, not the end of the literal.
+                        if chars.peek() == Some(&c) {
+                            current.push(chars.next().unwrap());
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'/') => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for b in chars.by_ref() {
+                    if prev == '*' && b == '/' {
+                        break;
+                    }
+                    prev = b;
+                }
+                current.push(' ');
+            }
+            ';' => {
+                if !current.trim().is_empty() {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+
+    statements
+}
+
+fn skip_line(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    for c in chars.by_ref() {
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+fn is_valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 48
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn table_options(opt: &Opt) -> Result<Option<String>> {
+    let mut options = vec![];
+
+    if let Some(compaction) = &opt.compaction {
+        options.push(format!("compaction = {{'class': '{}'}}", compaction));
+    }
+
+    if let Some(compression) = &opt.table_compression {
+        match compression.to_lowercase().as_str() {
+            "none" => options.push("compression = {}".to_string()),
+            _ => options.push(format!(
+                "compression = {{'sstable_compression': '{}'}}",
+                compression
+            )),
+        }
+    }
+
+    if let Some(caching) = &opt.caching {
+        let caching = match caching.to_lowercase().as_str() {
+            "all" => "{'keys': 'ALL', 'rows_per_partition': 'ALL'}",
+            "keys_only" => "{'keys': 'ALL', 'rows_per_partition': 'NONE'}",
+            "none" => "{'enabled': false}",
+            _ => return Err(anyhow!("Unsupported caching option: {}", caching)),
+        };
+        options.push(format!("caching = {}", caching));
+    }
+
+    if let Some(gc_grace_seconds) = opt.gc_grace_seconds {
+        options.push(format!("gc_grace_seconds = {}", gc_grace_seconds));
+    }
+
+    if let Some(default_ttl) = opt.default_ttl {
+        options.push(format!("default_time_to_live = {}", default_ttl));
+    }
+
+    if let Some(bloom_filter_fp_chance) = opt.bloom_filter_fp_chance {
+        options.push(format!(
+            "bloom_filter_fp_chance = {}",
+            bloom_filter_fp_chance
+        ));
+    }
+
+    if options.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(options.join(" AND ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_statements_on_semicolons() {
+        assert_eq!(
+            split_statements("CREATE TABLE a (k int PRIMARY KEY);\n\n;USE ks;"),
+            vec!["CREATE TABLE a (k int PRIMARY KEY)", "USE ks"]
+        );
+    }
+
+    #[test]
+    fn split_statements_ignores_quoted_semicolons() {
+        assert_eq!(
+            split_statements(
+                "INSERT INTO t (k, v) VALUES (1, 'a;''b'); SELECT \"odd;name\" FROM t"
+            ),
+            vec![
+                "INSERT INTO t (k, v) VALUES (1, 'a;''b')",
+                "SELECT \"odd;name\" FROM t"
+            ]
+        );
+    }
+
+    #[test]
+    fn split_statements_drops_comments() {
+        assert_eq!(
+            split_statements(
+                "-- first; comment\nUSE ks; // second; comment\nSELECT /* inline; */ 1 FROM t;"
+            ),
+            vec!["USE ks", "SELECT   1 FROM t"]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::Opt;
+use anyhow::{anyhow, Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+const DEFAULT_USERNAME: &str = "cassandra";
+const DEFAULT_PASSWORD: &str = "cassandra";
+
+/// A string that never appears in `Debug` or `Display` output, so it can be
+/// carried around in `Opt` without leaking into logs.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Secret(s.to_string()))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"<redacted>\"")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: Secret,
+}
+
+/// Resolves credentials in order of precedence: command line flags or their
+/// environment variables, the credentials file, an interactive prompt and
+/// finally the `cassandra`/`cassandra` defaults.
+pub fn resolve(opt: &Opt) -> Result<Credentials> {
+    let mut username = opt.username.clone();
+    let mut password = opt.password.clone();
+
+    if let Some(path) = &opt.credentials_file {
+        let (file_username, file_password) = read_credentials_file(path)?;
+        username = username.or(file_username);
+        password = password.or(file_password);
+    }
+
+    if opt.prompt_password {
+        if username.is_none() {
+            username = Some(prompt_username()?);
+        }
+        if password.is_none() {
+            let username = username.as_deref().unwrap_or(DEFAULT_USERNAME);
+            password = Some(prompt_password(username)?);
+        }
+    }
+
+    Ok(Credentials {
+        username: username.unwrap_or_else(|| DEFAULT_USERNAME.to_string()),
+        password: password.unwrap_or_else(|| Secret(DEFAULT_PASSWORD.to_string())),
+    })
+}
+
+/// Reads `key = value` lines, which also covers the `[authentication]`
+/// section of a cqlshrc file. Comments and section headers are ignored.
+fn read_credentials_file(path: &Path) -> Result<(Option<String>, Option<Secret>)> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open credentials file {}", path.display()))?;
+
+    let mut username = None;
+    let mut password = None;
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with(';')
+            || line.starts_with('[')
+        {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().to_string();
+            match key.trim() {
+                "username" => username = Some(value),
+                "password" => password = Some(Secret(value)),
+                _ => {}
+            }
+        }
+    }
+
+    Ok((username, password))
+}
+
+fn prompt_username() -> Result<String> {
+    eprint!("Username: ");
+    io::stderr().flush()?;
+    let mut username = String::new();
+    io::stdin().read_line(&mut username)?;
+    Ok(username.trim().to_string())
+}
+
+fn prompt_password(username: &str) -> Result<Secret> {
+    eprint!("Password for {}: ", username);
+    io::stderr().flush()?;
+
+    terminal::enable_raw_mode()?;
+    let mut password = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow!("Password prompt cancelled"))
+                }
+                KeyCode::Char(c) => password.push(c),
+                KeyCode::Backspace => {
+                    password.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    eprintln!();
+
+    result.map(|_| Secret(password))
+}
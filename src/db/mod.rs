@@ -1,2 +1,4 @@
 pub mod connection;
+pub mod credentials;
 pub mod models;
+pub mod topology;
@@ -10,19 +10,20 @@ use scylla::{FromRow, SerializeRow};
 use uuid::Uuid;
 
 pub const DDL_CACHE: &str = r#"
-    CREATE KEYSPACE IF NOT EXISTS skylar WITH replication =
-    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>};
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
 
-    USE skylar;
-    CREATE TABLE IF NOT EXISTS skylar.cache
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
     (
         device_id   uuid PRIMARY KEY,
         temperature int
-    )
+    )<TABLE_OPTIONS>
 "#;
 
 pub const INSERT_KEY_VALUE: &str = "
-    INSERT INTO skylar.cache
+    INSERT INTO <KEYSPACE>.<TABLE>
     (
         device_id,
         temperature
@@ -34,15 +35,22 @@ pub const SELECT_KEY_VALUE: &str = "
     SELECT
         device_id,
         temperature
-    FROM skylar.cache
+    FROM <KEYSPACE>.<TABLE>
     WHERE device_id = ?
 ";
 
 static SEQUENTIAL_INDEX_A: AtomicUsize = AtomicUsize::new(0);
 
+/// The high half of every device id. The low half is the device's index, so
+/// every run draws from the same keys and a later run can re-read the keys an
+/// earlier one wrote.
+const DEVICE_ID_PREFIX: u64 = 0x6465_7669_6365_0000;
+
 static DEVICES: Lazy<Vec<Uuid>> = Lazy::new(|| {
     let size = 1000000;
-    (0..size).map(|_| Uuid::new_v4()).collect()
+    (0..size)
+        .map(|index| Uuid::from_u64_pair(DEVICE_ID_PREFIX, index))
+        .collect()
 });
 
 static WEIGHTS_NORMAL: Lazy<WeightedIndex<usize>> = Lazy::new(|| {
@@ -131,6 +139,22 @@ pub fn device_id(distribution: &str) -> Uuid {
     DEVICES[dist.sample(&mut rng)]
 }
 
+/// The number of devices ids are drawn from.
+pub fn device_count() -> usize {
+    DEVICES.len()
+}
+
+pub fn device_at(index: usize) -> Uuid {
+    DEVICES[index]
+}
+
+pub fn device_index(device_id: &Uuid) -> Option<usize> {
+    match device_id.as_u64_pair() {
+        (DEVICE_ID_PREFIX, index) if (index as usize) < DEVICES.len() => Some(index as usize),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Clone, SerializeRow, FromRow)]
 pub struct Cache {
     pub device_id: Uuid,
@@ -154,6 +178,14 @@ impl WritePayload for Cache {
             temperature: rng.gen_range(0..100)
         }
     }
+
+    fn size(&self) -> usize {
+        16 + 8
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        self.device_id = other.device_id;
+    }
 }
 
 impl ReadPayload for CacheValues {
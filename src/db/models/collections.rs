@@ -0,0 +1,312 @@
+use crate::db::models::timeseries::sled_id;
+use crate::db::models::{digest, ReadPayload, WritePayload};
+use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::Rng;
+use scylla::frame::response::cql_to_rust::FromRowError;
+use scylla::frame::response::result::Row;
+use scylla::serialize::row::{RowSerializationContext, SerializeRow};
+use scylla::serialize::{RowWriter, SerializationError};
+use scylla::{FromRow, FromUserType, SerializeRow, SerializeValue};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use uuid::Uuid;
+
+pub const DDL_COLLECTIONS: &str = r#"
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
+
+    USE <KEYSPACE>;
+    CREATE TYPE IF NOT EXISTS <KEYSPACE>.location
+    (
+        rack text,
+        sled int,
+        zone text
+    );
+
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
+    (
+        key      uuid PRIMARY KEY,
+        tags     map<text, text>,
+        members  set<uuid>,
+        readings list<int>,
+        location frozen<location>,
+        labels   frozen<set<text>>
+    )<TABLE_OPTIONS>
+"#;
+
+pub const INSERT_COLLECTIONS: &str = "
+    INSERT INTO <KEYSPACE>.<TABLE>
+    (
+        key,
+        tags,
+        members,
+        readings,
+        location,
+        labels
+    )
+    VALUES (?, ?, ?, ?, ?, ?)
+";
+
+pub const APPEND_COLLECTIONS: &str = "
+    UPDATE <KEYSPACE>.<TABLE>
+    SET tags = tags + :tags, members = members + :members, readings = readings + :readings
+    WHERE key = :key
+";
+
+pub const UPDATE_COLLECTIONS: &str = "
+    UPDATE <KEYSPACE>.<TABLE>
+    SET tags[:tag] = :value, members = members - :removed
+    WHERE key = :key
+";
+
+pub const SELECT_COLLECTIONS: &str = "
+    SELECT
+        key,
+        tags,
+        members,
+        readings,
+        location,
+        labels
+    FROM <KEYSPACE>.<TABLE>
+    WHERE key = ?
+";
+
+static COLLECTION_SIZE: Lazy<usize> = Lazy::new(|| {
+    env::var("COLLECTION_SIZE")
+        .unwrap_or_else(|_| "10".to_string())
+        .parse()
+        .expect("Failed to parse COLLECTION_SIZE")
+});
+
+static COLLECTION_OPERATION: Lazy<String> =
+    Lazy::new(|| env::var("COLLECTION_OPERATION").unwrap_or_else(|_| "mixed".to_string()));
+
+#[derive(Debug, Clone, SerializeValue, FromUserType)]
+pub struct Location {
+    pub rack: String,
+    pub sled: i32,
+    pub zone: String,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct CollectionsInsert {
+    pub key: Uuid,
+    pub tags: HashMap<String, String>,
+    pub members: HashSet<Uuid>,
+    pub readings: Vec<i32>,
+    pub location: Location,
+    pub labels: HashSet<String>,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct CollectionsAppend {
+    pub key: Uuid,
+    pub tags: HashMap<String, String>,
+    pub members: HashSet<Uuid>,
+    pub readings: Vec<i32>,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct CollectionsUpdate {
+    pub key: Uuid,
+    pub tag: String,
+    pub value: String,
+    pub removed: HashSet<Uuid>,
+}
+
+/// Empty non-frozen collections are read back as null.
+#[derive(FromRow)]
+struct CollectionsRow {
+    key: Uuid,
+    tags: Option<HashMap<String, String>>,
+    members: Option<HashSet<Uuid>>,
+    readings: Option<Vec<i32>>,
+    location: Option<Location>,
+    labels: Option<HashSet<String>>,
+}
+
+/// A full row insert, an append to the non-frozen collections, or an update of
+/// single elements, chosen per write by `COLLECTION_OPERATION`. Rows read back
+/// from the table are returned as `Insert`.
+#[derive(Debug, Clone)]
+pub enum Collections {
+    Insert(CollectionsInsert),
+    Append(CollectionsAppend),
+    Update(CollectionsUpdate),
+}
+
+impl Collections {
+    fn key(&self) -> Uuid {
+        match self {
+            Collections::Insert(insert) => insert.key,
+            Collections::Append(append) => append.key,
+            Collections::Update(update) => update.key,
+        }
+    }
+}
+
+impl SerializeRow for Collections {
+    fn serialize(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Result<(), SerializationError> {
+        match self {
+            Collections::Insert(insert) => insert.serialize(ctx, writer),
+            Collections::Append(append) => append.serialize(ctx, writer),
+            Collections::Update(update) => update.serialize(ctx, writer),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl FromRow for Collections {
+    fn from_row(row: Row) -> Result<Self, FromRowError> {
+        let row = CollectionsRow::from_row(row)?;
+        Ok(Collections::Insert(CollectionsInsert {
+            key: row.key,
+            tags: row.tags.unwrap_or_default(),
+            members: row.members.unwrap_or_default(),
+            readings: row.readings.unwrap_or_default(),
+            location: row.location.unwrap_or(Location {
+                rack: String::new(),
+                sled: 0,
+                zone: String::new(),
+            }),
+            labels: row.labels.unwrap_or_default(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct CollectionsValues {
+    key: Uuid,
+}
+
+/// Map keys are drawn from `tag-0` up to the collection size, so element
+/// updates overwrite entries an insert wrote rather than adding new ones.
+fn tag(index: usize) -> String {
+    format!("tag-{}", index)
+}
+
+/// Set members are derived from the row's key, like map keys from their
+/// index, so an update can remove a member an insert of that row wrote.
+fn member(key: Uuid, index: usize) -> Uuid {
+    Uuid::from_u64_pair(digest(&(key, index)), index as u64)
+}
+
+fn tag_value() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), 16)
+}
+
+fn tags_size(tags: &HashMap<String, String>) -> usize {
+    tags.iter().map(|(k, v)| k.len() + v.len()).sum()
+}
+
+impl WritePayload for Collections {
+    fn insert_query() -> &'static str {
+        INSERT_COLLECTIONS
+    }
+
+    fn write_queries() -> Vec<&'static str> {
+        vec![INSERT_COLLECTIONS, APPEND_COLLECTIONS, UPDATE_COLLECTIONS]
+    }
+
+    fn write_query_index(&self) -> usize {
+        match self {
+            Collections::Insert(_) => 0,
+            Collections::Append(_) => 1,
+            Collections::Update(_) => 2,
+        }
+    }
+
+    /// Inserts write `COLLECTION_SIZE` elements into every collection, appends
+    /// add one element to each non-frozen collection and updates set one map
+    /// entry and remove one set element. mixed issues 40% inserts, 40% appends
+    /// and 20% updates.
+    fn insert_values(distribution: &str) -> Self {
+        let mut rng = rand::thread_rng();
+        let key = sled_id(distribution);
+        let size = *COLLECTION_SIZE;
+        let operation = match COLLECTION_OPERATION.as_str() {
+            "mixed" => match rng.gen_range(0..10) {
+                0..=3 => "insert",
+                4..=7 => "append",
+                _ => "update",
+            },
+            operation => operation,
+        };
+        match operation {
+            "append" => Collections::Append(CollectionsAppend {
+                key,
+                tags: HashMap::from([(tag(rng.gen_range(0..size.max(1))), tag_value())]),
+                members: HashSet::from([Uuid::new_v4()]),
+                readings: vec![rng.gen()],
+            }),
+            "update" => Collections::Update(CollectionsUpdate {
+                key,
+                tag: tag(rng.gen_range(0..size.max(1))),
+                value: tag_value(),
+                removed: HashSet::from([member(key, rng.gen_range(0..size.max(1)))]),
+            }),
+            _ => Collections::Insert(CollectionsInsert {
+                key,
+                tags: (0..size).map(|i| (tag(i), tag_value())).collect(),
+                members: (0..size).map(|i| member(key, i)).collect(),
+                readings: (0..size).map(|_| rng.gen()).collect(),
+                location: Location {
+                    rack: format!("rack-{}", rng.gen_range(0..10)),
+                    sled: rng.gen_range(0..32),
+                    zone: format!("zone-{}", rng.gen_range(0..3)),
+                },
+                labels: (0..size).map(|i| format!("label-{}", i)).collect(),
+            }),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Collections::Insert(insert) => {
+                16 + tags_size(&insert.tags)
+                    + 16 * insert.members.len()
+                    + 4 * insert.readings.len()
+                    + insert.location.rack.len()
+                    + 4
+                    + insert.location.zone.len()
+                    + insert.labels.iter().map(|l| l.len()).sum::<usize>()
+            }
+            Collections::Append(append) => {
+                16 + tags_size(&append.tags) + 16 * append.members.len() + 4 * append.readings.len()
+            }
+            Collections::Update(update) => {
+                16 + update.tag.len() + update.value.len() + 16 * update.removed.len()
+            }
+        }
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        let key = other.key();
+        match self {
+            Collections::Insert(insert) => insert.key = key,
+            Collections::Append(append) => append.key = key,
+            Collections::Update(update) => update.key = key,
+        }
+    }
+}
+
+impl ReadPayload for CollectionsValues {
+    fn select_query() -> &'static str {
+        SELECT_COLLECTIONS
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        CollectionsValues {
+            key: sled_id(distribution),
+        }
+    }
+}
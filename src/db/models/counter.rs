@@ -0,0 +1,194 @@
+use crate::db::models::timeseries::sled_id;
+use crate::db::models::{qualify, ReadPayload, WritePayload};
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use scylla::frame::value::Counter;
+use scylla::{FromRow, SerializeRow, Session};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const DDL_COUNTER: &str = r#"
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
+
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
+    (
+        key   uuid PRIMARY KEY,
+        total counter
+    )<TABLE_OPTIONS>
+"#;
+
+pub const UPDATE_COUNTER: &str = "
+    UPDATE <KEYSPACE>.<TABLE>
+    SET total = total + ?
+    WHERE key = ?
+";
+
+pub const SELECT_COUNTER: &str = "
+    SELECT
+        key,
+        total
+    FROM <KEYSPACE>.<TABLE>
+    WHERE key = ?
+";
+
+const SELECT_ANY_COUNTER: &str = "SELECT key FROM <KEYSPACE>.<TABLE> LIMIT 1";
+
+/// Increments per key: those the cluster acknowledged, and those that failed
+/// and may or may not have been applied.
+#[derive(Debug, Default, Clone, Copy)]
+struct Increments {
+    acknowledged: i64,
+    uncertain: i64,
+}
+
+static INCREMENTS: Lazy<Mutex<HashMap<Uuid, Increments>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct CounterUpdate {
+    pub key: Uuid,
+    pub total: Counter,
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct CounterValues {
+    key: Uuid,
+}
+
+impl WritePayload for CounterUpdate {
+    fn insert_query() -> &'static str {
+        UPDATE_COUNTER
+    }
+
+    fn insert_values(distribution: &str) -> Self {
+        CounterUpdate {
+            key: sled_id(distribution),
+            total: Counter(rand::thread_rng().gen_range(1..=10)),
+        }
+    }
+
+    fn size(&self) -> usize {
+        16 + 8
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        self.key = other.key;
+    }
+
+    fn acknowledge(&self, acknowledged: bool) {
+        let mut increments = INCREMENTS.lock().unwrap();
+        let entry = increments.entry(self.key).or_default();
+        if acknowledged {
+            entry.acknowledged += self.total.0;
+        } else {
+            entry.uncertain += self.total.0;
+        }
+    }
+}
+
+impl ReadPayload for CounterValues {
+    fn select_query() -> &'static str {
+        SELECT_COUNTER
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        CounterValues {
+            key: sled_id(distribution),
+        }
+    }
+}
+
+/// Warns when the counter table already holds values, since validation
+/// compares against the increments made during this run only.
+pub async fn check_empty(session: &Session, opt: &Opt) -> Result<()> {
+    let query = qualify(SELECT_ANY_COUNTER, &opt.keyspace, &opt.table());
+    let rows = session
+        .query_unpaged(query, &[])
+        .await
+        .map_err(|e| anyhow!("Error checking counter table: {}", e))?
+        .rows_num()
+        .unwrap_or(0);
+    if rows > 0 {
+        warn!("Counter table is not empty, validation will report pre-existing counts as over-counted. Use --truncate or --drop-keyspace between runs.");
+    }
+    Ok(())
+}
+
+/// Reads back every counter incremented during the run and compares it with
+/// the increments the cluster acknowledged. A counter above the acknowledged
+/// total is explained by failed increments that were applied anyway, unless it
+/// exceeds those too. A counter below it has lost acknowledged increments.
+pub async fn validate(session: &Session, opt: &Opt) -> Result<()> {
+    let increments: Vec<(Uuid, Increments)> = INCREMENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(key, increments)| (*key, *increments))
+        .collect();
+
+    let query = qualify(SELECT_COUNTER, &opt.keyspace, &opt.table());
+    let statement = session
+        .prepare(query)
+        .await
+        .map_err(|e| anyhow!("Error preparing counter validation: {}", e))?;
+
+    let results: Vec<Result<(Increments, i64)>> = stream::iter(increments)
+        .map(|(key, increments)| {
+            let statement = statement.clone();
+            async move {
+                let count = session
+                    .execute_unpaged(&statement, (key,))
+                    .await
+                    .map_err(|e| anyhow!("Error reading counter {}: {}", key, e))?
+                    .maybe_first_row_typed::<CounterUpdate>()
+                    .map_err(|e| anyhow!("Error reading counter {}: {}", key, e))?
+                    .map(|row| row.total.0)
+                    .unwrap_or(0);
+                Ok((increments, count))
+            }
+        })
+        .buffer_unordered(64)
+        .collect()
+        .await;
+
+    let (mut matched, mut explained, mut over, mut under, mut failed) = (0, 0, 0, 0, 0);
+    let (mut acknowledged_total, mut counted_total) = (0, 0);
+    for result in results {
+        let Ok((increments, count)) = result else {
+            failed += 1;
+            continue;
+        };
+        acknowledged_total += increments.acknowledged;
+        counted_total += count;
+        if count == increments.acknowledged {
+            matched += 1;
+        } else if count < increments.acknowledged {
+            under += 1;
+        } else if count <= increments.acknowledged + increments.uncertain {
+            explained += 1;
+        } else {
+            over += 1;
+        }
+    }
+
+    info!(
+        "Counter validation: acknowledged={} counted={} matched={} explained_by_failed_writes={} over_counted={} under_counted={} unreadable={}",
+        acknowledged_total, counted_total, matched, explained, over, under, failed
+    );
+    if under > 0 || over > 0 {
+        warn!(
+            "Counter validation failed: {} counters lost acknowledged increments, {} counted more than was written",
+            under, over
+        );
+    }
+
+    Ok(())
+}
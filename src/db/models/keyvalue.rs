@@ -0,0 +1,407 @@
+use crate::db::connection::parse_consistency;
+use crate::db::models::cache::{device_at, device_count, device_id, device_index};
+use crate::db::models::{digest, qualify, ReadPayload, WritePayload};
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString, Distribution, WeightedIndex};
+use rand::Rng;
+use scylla::frame::response::cql_to_rust::{FromCqlVal, FromCqlValError};
+use scylla::frame::response::result::{ColumnType, CqlValue};
+use scylla::serialize::value::SerializeValue;
+use scylla::serialize::writers::WrittenCellProof;
+use scylla::serialize::{CellWriter, SerializationError};
+use scylla::{FromRow, SerializeRow, Session};
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+pub const DDL_KEY_VALUE: &str = r#"
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
+
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
+    (
+        key   uuid PRIMARY KEY,
+        value <VALUE_TYPE>
+    )<TABLE_OPTIONS>
+"#;
+
+pub const INSERT_KEY_VALUE: &str = "
+    INSERT INTO <KEYSPACE>.<TABLE>
+    (
+        key,
+        value
+    )
+    VALUES (?, ?)
+";
+
+pub const SELECT_KEY_VALUE: &str = "
+    SELECT
+        key,
+        value
+    FROM <KEYSPACE>.<TABLE>
+    WHERE key = ?
+";
+
+/// Value sizes in bytes, drawn from either a fixed size, a uniform range
+/// such as `1KB..10KB` or a histogram such as `80% 1KB, 19% 10KB, 1% 1MB`.
+#[derive(Debug, Clone)]
+pub enum ValueSizes {
+    Fixed(usize),
+    Uniform(usize, usize),
+    Histogram(Vec<usize>, WeightedIndex<f64>),
+}
+
+impl ValueSizes {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.contains('%') {
+            let mut sizes = vec![];
+            let mut weights = vec![];
+            for bucket in spec.split(',') {
+                let (weight, size) = bucket
+                    .split_once('%')
+                    .ok_or_else(|| anyhow!("Invalid value size bucket: {}", bucket))?;
+                weights.push(weight.trim().parse::<f64>()?);
+                sizes.push(parse_bytes(size.trim_start_matches([' ', ':']))?);
+            }
+            let index = WeightedIndex::new(weights)?;
+            Ok(ValueSizes::Histogram(sizes, index))
+        } else if let Some((min, max)) = spec.split_once("..") {
+            let (min, max) = (parse_bytes(min)?, parse_bytes(max)?);
+            if min > max {
+                return Err(anyhow!("Invalid value size range: {}", spec));
+            }
+            Ok(ValueSizes::Uniform(min, max))
+        } else {
+            Ok(ValueSizes::Fixed(parse_bytes(spec)?))
+        }
+    }
+
+    pub fn sample(&self) -> usize {
+        let mut rng = rand::thread_rng();
+        match self {
+            ValueSizes::Fixed(size) => *size,
+            ValueSizes::Uniform(min, max) => rng.gen_range(*min..=*max),
+            ValueSizes::Histogram(sizes, index) => sizes[index.sample(&mut rng)],
+        }
+    }
+}
+
+fn parse_bytes(spec: &str) -> Result<usize> {
+    let spec = spec.trim().to_uppercase();
+    let (number, multiplier) = if let Some(n) = spec.strip_suffix("MB") {
+        (n, 1024 * 1024)
+    } else if let Some(n) = spec.strip_suffix("KB") {
+        (n, 1024)
+    } else if let Some(n) = spec.strip_suffix('B') {
+        (n, 1)
+    } else {
+        (spec.as_str(), 1)
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid size: {}", spec))?;
+    Ok((number * multiplier as f64) as usize)
+}
+
+static VALUE_SIZES: Lazy<ValueSizes> = Lazy::new(|| {
+    let spec = env::var("VALUE_SIZE").unwrap_or_else(|_| "1KB".to_string());
+    ValueSizes::parse(&spec).expect("Failed to parse VALUE_SIZE")
+});
+
+static VALUE_TYPE: Lazy<String> =
+    Lazy::new(|| env::var("VALUE_TYPE").unwrap_or_else(|_| "blob".to_string()));
+
+/// A `blob` or `text` value, depending on the column type the table was created with.
+#[derive(Clone, Hash)]
+pub enum Value {
+    Blob(Vec<u8>),
+    Text(String),
+}
+
+impl Value {
+    fn generate(size: usize) -> Self {
+        match VALUE_TYPE.as_str() {
+            "text" => Value::Text(Alphanumeric.sample_string(&mut rand::thread_rng(), size)),
+            _ => {
+                let mut blob = vec![0u8; size];
+                rand::thread_rng().fill(&mut blob[..]);
+                Value::Blob(blob)
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Value::Blob(blob) => blob.len(),
+            Value::Text(text) => text.len(),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Blob(blob) => write!(f, "blob({} bytes)", blob.len()),
+            Value::Text(text) => write!(f, "text({} bytes)", text.len()),
+        }
+    }
+}
+
+impl SerializeValue for Value {
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        match self {
+            Value::Blob(blob) => blob.serialize(typ, writer),
+            Value::Text(text) => text.serialize(typ, writer),
+        }
+    }
+}
+
+impl FromCqlVal<CqlValue> for Value {
+    fn from_cql(cql_val: CqlValue) -> Result<Self, FromCqlValError> {
+        match cql_val {
+            CqlValue::Blob(blob) => Ok(Value::Blob(blob)),
+            CqlValue::Text(text) => Ok(Value::Text(text)),
+            _ => Err(FromCqlValError::BadCqlType),
+        }
+    }
+}
+
+/// A bit per device in the key pool, set once any write to the key completes.
+struct Bitmap(Vec<AtomicU64>);
+
+impl Bitmap {
+    fn new(bits: usize) -> Self {
+        Bitmap((0..bits.div_ceil(64)).map(|_| AtomicU64::new(0)).collect())
+    }
+
+    fn set(&self, index: usize) {
+        self.0[index / 64].fetch_or(1 << (index % 64), Ordering::Relaxed);
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.0[index / 64].load(Ordering::Relaxed) & (1 << (index % 64)) != 0
+    }
+}
+
+/// Keys with an acknowledged write, and keys with a write that failed and may
+/// or may not have been applied.
+static ACKNOWLEDGED: Lazy<Bitmap> = Lazy::new(|| Bitmap::new(device_count()));
+static FAILED: Lazy<Bitmap> = Lazy::new(|| Bitmap::new(device_count()));
+
+/// Whether to record written keys for verification.
+static VERIFY: Lazy<bool> = Lazy::new(|| env::var("VERIFY").is_ok_and(|verify| verify == "true"));
+
+/// Keys listed individually when verification finds acknowledged writes missing.
+const MISSING_KEYS_LOGGED: usize = 20;
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct KeyValue {
+    pub key: Uuid,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct KeyValueValues {
+    key: Uuid,
+}
+
+impl WritePayload for KeyValue {
+    fn insert_query() -> &'static str {
+        INSERT_KEY_VALUE
+    }
+
+    fn insert_values(distribution: &str) -> Self {
+        KeyValue {
+            key: device_id(distribution),
+            value: Value::generate(VALUE_SIZES.sample()),
+        }
+    }
+
+    fn size(&self) -> usize {
+        16 + self.value.len()
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        self.key = other.key;
+    }
+
+    fn acknowledge(&self, acknowledged: bool) {
+        if !*VERIFY {
+            return;
+        }
+        if let Some(index) = device_index(&self.key) {
+            if acknowledged {
+                ACKNOWLEDGED.set(index);
+            } else {
+                FAILED.set(index);
+            }
+        }
+    }
+
+    fn validation_digest(&self) -> Option<(Uuid, u64)> {
+        Some((self.key, digest(&self.value)))
+    }
+}
+
+impl ReadPayload for KeyValueValues {
+    fn select_query() -> &'static str {
+        SELECT_KEY_VALUE
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        KeyValueValues {
+            key: device_id(distribution),
+        }
+    }
+
+    fn validation_key(&self) -> Option<Uuid> {
+        Some(self.key)
+    }
+}
+
+/// Every key written during the run, with whether any write to it was
+/// acknowledged.
+pub fn written_keys() -> Vec<(Uuid, bool)> {
+    (0..device_count())
+        .filter_map(|index| {
+            let acknowledged = ACKNOWLEDGED.get(index);
+            (acknowledged || FAILED.get(index)).then(|| (device_at(index), acknowledged))
+        })
+        .collect()
+}
+
+/// Writes the keys written during the run to --verify-file, one per line
+/// followed by `acknowledged` or `failed`, so --verify-only can re-read them
+/// after the run, for example once a node has been restarted.
+pub fn save_written_keys(opt: &Opt, keys: &[(Uuid, bool)]) -> Result<()> {
+    let path = &opt.verify_file;
+    let mut file = BufWriter::new(
+        File::create(path).map_err(|e| anyhow!("Error creating {}: {}", path.display(), e))?,
+    );
+    for (key, acknowledged) in keys {
+        let state = if *acknowledged {
+            "acknowledged"
+        } else {
+            "failed"
+        };
+        writeln!(file, "{} {}", key, state)
+            .map_err(|e| anyhow!("Error writing keys to {}: {}", path.display(), e))?;
+    }
+    file.flush()
+        .map_err(|e| anyhow!("Error writing keys to {}: {}", path.display(), e))?;
+    info!("Wrote {} written keys to {}", keys.len(), path.display());
+    Ok(())
+}
+
+/// Reads the keys an earlier run saved with `save_written_keys`.
+pub fn load_written_keys(opt: &Opt) -> Result<Vec<(Uuid, bool)>> {
+    let path = &opt.verify_file;
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Error reading {}: {}", path.display(), e))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            parse_written_key(line)
+                .ok_or_else(|| anyhow!("Invalid key in {}: {}", path.display(), line))
+        })
+        .collect()
+}
+
+fn parse_written_key(line: &str) -> Option<(Uuid, bool)> {
+    let (key, state) = line.trim().split_once(' ')?;
+    let acknowledged = match state {
+        "acknowledged" => true,
+        "failed" => false,
+        _ => return None,
+    };
+    Some((Uuid::parse_str(key).ok()?, acknowledged))
+}
+
+/// Re-reads the given keys at the verify consistency level. An acknowledged
+/// key that reads back empty is a lost write. A key whose writes all failed
+/// but reads back present had a write applied regardless, or held a row from
+/// an earlier run.
+pub async fn verify(session: &Session, opt: &Opt, keys: Vec<(Uuid, bool)>) -> Result<()> {
+    let level = opt
+        .verify_consistency_level
+        .as_deref()
+        .unwrap_or(&opt.consistency_level);
+    let query = qualify(SELECT_KEY_VALUE, &opt.keyspace, &opt.table());
+    let mut statement = session
+        .prepare(query)
+        .await
+        .map_err(|e| anyhow!("Error preparing verification: {}", e))?;
+    statement.set_consistency(parse_consistency(level));
+
+    info!("Verifying {} keys at {}", keys.len(), level.to_uppercase());
+    let results: Vec<(Uuid, bool, Result<bool>)> = stream::iter(keys)
+        .map(|(key, acknowledged)| {
+            let statement = statement.clone();
+            async move {
+                let present = session
+                    .execute_unpaged(&statement, (key,))
+                    .await
+                    .map_err(|e| anyhow!("Error reading key {}: {}", key, e))
+                    .map(|result| result.rows_num().unwrap_or(0) > 0);
+                (key, acknowledged, present)
+            }
+        })
+        .buffer_unordered(64)
+        .collect()
+        .await;
+
+    let (mut acknowledged_present, mut acknowledged_missing) = (0, 0);
+    let (mut unacknowledged_present, mut unacknowledged_missing, mut unreadable) = (0, 0, 0);
+    for (key, acknowledged, present) in results {
+        match (acknowledged, present) {
+            (_, Err(e)) => {
+                unreadable += 1;
+                warn!("{}", e);
+            }
+            (true, Ok(true)) => acknowledged_present += 1,
+            (true, Ok(false)) => {
+                if acknowledged_missing < MISSING_KEYS_LOGGED {
+                    warn!("Acknowledged write missing for key {}", key);
+                }
+                acknowledged_missing += 1;
+            }
+            (false, Ok(true)) => unacknowledged_present += 1,
+            (false, Ok(false)) => unacknowledged_missing += 1,
+        }
+    }
+
+    info!(
+        "Lost write verification: acknowledged={} acknowledged_present={} acknowledged_missing={} unacknowledged={} unacknowledged_present={} unacknowledged_missing={} unreadable={}",
+        acknowledged_present + acknowledged_missing,
+        acknowledged_present,
+        acknowledged_missing,
+        unacknowledged_present + unacknowledged_missing,
+        unacknowledged_present,
+        unacknowledged_missing,
+        unreadable
+    );
+    if acknowledged_missing > 0 {
+        warn!(
+            "Lost write verification failed: {} acknowledged writes are missing",
+            acknowledged_missing
+        );
+    }
+
+    Ok(())
+}
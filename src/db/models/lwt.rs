@@ -0,0 +1,278 @@
+use crate::db::models::timeseries::sled_id;
+use crate::db::models::{ReadPayload, WritePayload};
+use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::Rng;
+use scylla::frame::response::cql_to_rust::FromRowError;
+use scylla::frame::response::result::{CqlValue, Row};
+use scylla::serialize::row::{RowSerializationContext, SerializeRow};
+use scylla::serialize::{RowWriter, SerializationError};
+use scylla::{FromRow, QueryResult, SerializeRow};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+pub const DDL_LWT: &str = r#"
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
+
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
+    (
+        key     uuid PRIMARY KEY,
+        version bigint,
+        value   text
+    )<TABLE_OPTIONS>
+"#;
+
+pub const INSERT_LWT: &str = "
+    INSERT INTO <KEYSPACE>.<TABLE>
+    (
+        key,
+        version,
+        value
+    )
+    VALUES (?, ?, ?)
+    IF NOT EXISTS
+";
+
+pub const UPDATE_LWT: &str = "
+    UPDATE <KEYSPACE>.<TABLE>
+    SET version = :version, value = :value
+    WHERE key = :key
+    IF version = :expected
+";
+
+pub const DELETE_LWT: &str = "
+    DELETE FROM <KEYSPACE>.<TABLE>
+    WHERE key = :key
+    IF version = :expected
+";
+
+pub const SELECT_LWT: &str = "
+    SELECT
+        key,
+        version,
+        value
+    FROM <KEYSPACE>.<TABLE>
+    WHERE key = ?
+";
+
+/// The version each key was last seen at, learned from applied writes and
+/// from the current values returned by ones that didn't apply. Updates and
+/// deletes compare against it, so they only fail to apply when another
+/// writer got to the key first.
+static KNOWN_VERSIONS: Lazy<Mutex<HashMap<Uuid, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static LWT_OPERATION: Lazy<String> =
+    Lazy::new(|| env::var("LWT_OPERATION").unwrap_or_else(|_| "mixed".to_string()));
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct LwtInsert {
+    pub key: Uuid,
+    pub version: i64,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct LwtUpdate {
+    pub key: Uuid,
+    pub version: i64,
+    pub value: String,
+    pub expected: i64,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct LwtDelete {
+    pub key: Uuid,
+    pub expected: i64,
+}
+
+/// One of the conditional statements, chosen per write by `LWT_OPERATION`.
+/// Rows read back from the table are returned as `Insert`.
+#[derive(Debug, Clone)]
+pub enum Lwt {
+    Insert(LwtInsert),
+    Update(LwtUpdate),
+    Delete(LwtDelete),
+}
+
+impl Lwt {
+    fn key(&self) -> Uuid {
+        match self {
+            Lwt::Insert(insert) => insert.key,
+            Lwt::Update(update) => update.key,
+            Lwt::Delete(delete) => delete.key,
+        }
+    }
+}
+
+impl SerializeRow for Lwt {
+    fn serialize(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Result<(), SerializationError> {
+        match self {
+            Lwt::Insert(insert) => insert.serialize(ctx, writer),
+            Lwt::Update(update) => update.serialize(ctx, writer),
+            Lwt::Delete(delete) => delete.serialize(ctx, writer),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl FromRow for Lwt {
+    fn from_row(row: Row) -> Result<Self, FromRowError> {
+        LwtInsert::from_row(row).map(Lwt::Insert)
+    }
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct LwtValues {
+    key: Uuid,
+}
+
+impl WritePayload for Lwt {
+    fn insert_query() -> &'static str {
+        INSERT_LWT
+    }
+
+    fn write_queries() -> Vec<&'static str> {
+        vec![INSERT_LWT, UPDATE_LWT, DELETE_LWT]
+    }
+
+    fn write_query_index(&self) -> usize {
+        match self {
+            Lwt::Insert(_) => 0,
+            Lwt::Update(_) => 1,
+            Lwt::Delete(_) => 2,
+        }
+    }
+
+    /// mixed issues 30% inserts, 60% compare-and-set updates and 10% conditional
+    /// deletes. Updates and deletes of a key whose version isn't known, because
+    /// it hasn't been written or its last write timed out, are sent as inserts,
+    /// which learn the version when the key already exists.
+    fn insert_values(distribution: &str) -> Self {
+        let mut rng = rand::thread_rng();
+        let key = sled_id(distribution);
+        let operation = match LWT_OPERATION.as_str() {
+            "mixed" => match rng.gen_range(0..10) {
+                0..=2 => "insert",
+                3..=8 => "update",
+                _ => "delete",
+            },
+            operation => operation,
+        };
+        let known = KNOWN_VERSIONS.lock().unwrap().get(&key).copied();
+        match (operation, known) {
+            ("update", Some(expected)) => Lwt::Update(LwtUpdate {
+                key,
+                version: expected + 1,
+                value: Alphanumeric.sample_string(&mut rng, 16),
+                expected,
+            }),
+            ("delete", Some(expected)) => Lwt::Delete(LwtDelete { key, expected }),
+            _ => Lwt::Insert(LwtInsert {
+                key,
+                version: 0,
+                value: Alphanumeric.sample_string(&mut rng, 16),
+            }),
+        }
+    }
+
+    /// A write that timed out may or may not have applied, so the key's
+    /// version is unknown until it's learned again.
+    fn acknowledge(&self, acknowledged: bool) {
+        if !acknowledged {
+            KNOWN_VERSIONS.lock().unwrap().remove(&self.key());
+        }
+    }
+
+    /// Records the version a key is at after this write: its own when it
+    /// applied, otherwise the one the cluster returned, if the row exists.
+    fn observe(&self, result: &QueryResult) {
+        let Some(applied) = applied(result) else {
+            return;
+        };
+        let version = if applied {
+            match self {
+                Lwt::Insert(insert) => Some(insert.version),
+                Lwt::Update(update) => Some(update.version),
+                Lwt::Delete(_) => None,
+            }
+        } else {
+            current_version(result)
+        };
+        let mut versions = KNOWN_VERSIONS.lock().unwrap();
+        match version {
+            Some(version) => versions.insert(self.key(), version),
+            None => versions.remove(&self.key()),
+        };
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Lwt::Insert(insert) => 16 + 8 + insert.value.len(),
+            Lwt::Update(update) => 16 + 8 + 8 + update.value.len(),
+            Lwt::Delete(_) => 16 + 8,
+        }
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        let key = other.key();
+        match self {
+            Lwt::Insert(insert) => insert.key = key,
+            Lwt::Update(update) => update.key = key,
+            Lwt::Delete(delete) => delete.key = key,
+        }
+    }
+
+    fn is_delete(&self) -> bool {
+        matches!(self, Lwt::Delete(_))
+    }
+}
+
+/// The `[applied]` outcome of a conditional statement, or `None` for a plain
+/// write, whose result has no such column.
+pub fn applied(result: &QueryResult) -> Option<bool> {
+    let column = result
+        .col_specs()
+        .iter()
+        .position(|spec| spec.name == "[applied]")?;
+    match result.rows.as_ref()?.first()?.columns.get(column)? {
+        Some(CqlValue::Boolean(applied)) => Some(*applied),
+        _ => None,
+    }
+}
+
+/// The version of the row a conditional statement that didn't apply was
+/// compared against, or `None` when there's no such row.
+fn current_version(result: &QueryResult) -> Option<i64> {
+    let column = result
+        .col_specs()
+        .iter()
+        .position(|spec| spec.name == "version")?;
+    match result.rows.as_ref()?.first()?.columns.get(column)? {
+        Some(CqlValue::BigInt(version)) => Some(*version),
+        _ => None,
+    }
+}
+
+impl ReadPayload for LwtValues {
+    fn select_query() -> &'static str {
+        SELECT_LWT
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        LwtValues {
+            key: sled_id(distribution),
+        }
+    }
+}
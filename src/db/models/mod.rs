@@ -1,12 +1,78 @@
+use scylla::QueryResult;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
 pub mod timeseries;
 pub mod cache;
+pub mod collections;
+pub mod counter;
+pub mod keyvalue;
+pub mod lwt;
+pub mod widerow;
 
 pub trait WritePayload: Send + Sync + 'static {
     fn insert_query() -> &'static str;
     fn insert_values(distribution: &str) -> Self;
+    /// Approximate size of the row's column values in bytes, used to report throughput.
+    fn size(&self) -> usize;
+
+    /// Every statement the payload writes with, for payloads that mix operations.
+    fn write_queries() -> Vec<&'static str> {
+        vec![Self::insert_query()]
+    }
+
+    /// The index into `write_queries` of the statement these values are bound for.
+    fn write_query_index(&self) -> usize {
+        0
+    }
+
+    /// Moves these values into the same partition as `other`, so batches can be
+    /// grouped by partition key. `position` is their place in the batch, for
+    /// payloads that need it to keep the rows of a batch distinct.
+    fn colocate_with(&mut self, other: &Self, position: usize);
+
+    /// Whether the statement deletes data, leaving tombstones behind.
+    fn is_delete(&self) -> bool {
+        false
+    }
+
+    /// Called once a write completes, with whether the cluster acknowledged it.
+    fn acknowledge(&self, _acknowledged: bool) {}
+
+    /// Called with the result of an acknowledged write, for payloads that
+    /// learn from the rows the cluster returns, such as the current values a
+    /// conditional statement that didn't apply was compared against.
+    fn observe(&self, _result: &QueryResult) {}
+
+    /// The key written and a digest of its values, for payloads whose reads
+    /// can be validated against the last acknowledged write.
+    fn validation_digest(&self) -> Option<(Uuid, u64)> {
+        None
+    }
 }
 
 pub trait ReadPayload: Send + Sync + 'static {
     fn select_query() -> &'static str;
     fn select_values(distribution: &str) -> Self;
+
+    /// The key read, for payloads whose reads can be validated.
+    fn validation_key(&self) -> Option<Uuid> {
+        None
+    }
+}
+
+/// Fills in the `<KEYSPACE>` and `<TABLE>` placeholders used by every payload's
+/// queries, so independent runs can target their own tables.
+pub fn qualify(query: &str, keyspace: &str, table: &str) -> String {
+    query
+        .replace("<KEYSPACE>", keyspace)
+        .replace("<TABLE>", table)
+}
+
+/// A digest of a row's values, compared by reads in validation mode.
+pub fn digest<T: Hash>(values: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    values.hash(&mut hasher);
+    hasher.finish()
 }
@@ -1,4 +1,5 @@
 use crate::db::models::{ReadPayload, WritePayload};
+use crate::Opt;
 use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use rand::distributions::Distribution;
@@ -10,16 +11,15 @@ use scylla::{FromRow, SerializeRow};
 use std::env;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use uuid::Uuid;
-static SEQUENTIAL_INDEX_A: AtomicUsize = AtomicUsize::new(0);
 static SEQUENTIAL_INDEX_B: AtomicUsize = AtomicUsize::new(0);
 
 pub const DDL_TIMESERIES: &str = r#"
-    CREATE KEYSPACE IF NOT EXISTS skylar WITH replication =
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
     {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
     AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
 
-    USE skylar;
-    CREATE TABLE IF NOT EXISTS skylar.devices
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
     (
         kind             text,
         link_name        text,
@@ -35,7 +35,7 @@ pub const DDL_TIMESERIES: &str = r#"
         packets_received int,
         time             timestamp,
         PRIMARY KEY ((rack_id, sled_id), time)
-    )
+    )<TABLE_OPTIONS>
 "#;
 
 static POOL_RACKS: Lazy<Vec<Uuid>> = Lazy::new(|| {
@@ -122,15 +122,10 @@ static WEIGHTS_ZIPF: Lazy<WeightedIndex<usize>> = Lazy::new(|| {
     WeightedIndex::new(weights).unwrap()
 });
 
-pub fn rack_id(distribution: &str) -> Uuid {
-    let mut rng = rand::thread_rng();
-    match distribution {
-        "sequential" => {
-            let index = SEQUENTIAL_INDEX_A.fetch_add(1, Ordering::SeqCst) % POOL_RACKS.len();
-            POOL_RACKS[index]
-        }
-        _ => *POOL_RACKS.choose(&mut rng).unwrap(),
-    }
+/// The rack a sled is in. Writes and reads derive it from the sled, so a
+/// read for a sled finds the partition its rows were written to.
+pub fn sled_rack_id(sled_id: &Uuid) -> Uuid {
+    POOL_RACKS[(sled_id.as_u128() % POOL_RACKS.len() as u128) as usize]
 }
 
 pub fn sled_id(distribution: &str) -> Uuid {
@@ -153,7 +148,7 @@ pub fn sled_id(distribution: &str) -> Uuid {
 }
 
 pub const INSERT_DEVICE: &str = "
-    INSERT INTO skylar.devices
+    INSERT INTO <KEYSPACE>.<TABLE>
     (
         kind,
         link_name,
@@ -187,7 +182,7 @@ pub const SELECT_DEVICE: &str = "
         packets_sent,
         packets_received,
         time
-    FROM skylar.devices
+    FROM <KEYSPACE>.<TABLE>
     WHERE rack_id = ? AND sled_id = ? AND time > ?
 ";
 
@@ -215,6 +210,152 @@ pub struct DeviceValues {
     time: DateTime<Utc>,
 }
 
+/// Reads through a global secondary index or a materialized view.
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct DeviceIndexValues {
+    value: String,
+}
+
+/// Reads through a local secondary index, which needs the partition key too.
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct DeviceLocalIndexValues {
+    rack_id: Uuid,
+    sled_id: Uuid,
+    value: String,
+}
+
+/// Columns indexes and views can be created on. Their values are derived from
+/// `sled_id`, so reads through an index can look up a sled that was written.
+pub const INDEXABLE_COLUMNS: [&str; 3] = ["sled_model", "sled_serial", "zone_name"];
+
+const SELECT_DEVICE_COLUMNS: &str = "
+    SELECT
+        kind,
+        link_name,
+        rack_id,
+        sled_id,
+        sled_model,
+        sled_revision,
+        sled_serial,
+        zone_name,
+        bytes_sent,
+        bytes_received,
+        packets_sent,
+        packets_received,
+        time";
+
+static READ_PATH: Lazy<String> =
+    Lazy::new(|| env::var("READ_PATH").unwrap_or_else(|_| "base".to_string()));
+
+static READ_COLUMN: Lazy<String> =
+    Lazy::new(|| env::var("READ_COLUMN").unwrap_or_else(|_| "sled_serial".to_string()));
+
+static READ_ROWS: Lazy<i64> = Lazy::new(|| {
+    env::var("READ_ROWS")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .expect("Failed to parse READ_ROWS")
+});
+
+/// Reads `READ_ROWS` rows matching `READ_COLUMN` through the index or view `READ_PATH` names.
+static SELECT_DEVICE_INDEXED: Lazy<String> = Lazy::new(|| {
+    let column = READ_COLUMN.as_str();
+    let (from, filter) = match READ_PATH.as_str() {
+        "view" => (view_name(column), format!("{} = :value", column)),
+        "local-index" => (
+            "<KEYSPACE>.<TABLE>".to_string(),
+            format!(
+                "rack_id = :rack_id AND sled_id = :sled_id AND {} = :value",
+                column
+            ),
+        ),
+        _ => (
+            "<KEYSPACE>.<TABLE>".to_string(),
+            format!("{} = :value", column),
+        ),
+    };
+    format!(
+        "{}
+    FROM {}
+    WHERE {}
+    LIMIT {}
+",
+        SELECT_DEVICE_COLUMNS, from, filter, *READ_ROWS
+    )
+});
+
+fn view_name(column: &str) -> String {
+    format!("<KEYSPACE>.<TABLE>_by_{}", column)
+}
+
+/// The CREATE INDEX and CREATE MATERIALIZED VIEW statements for the columns
+/// given with --secondary-index, --local-index and --materialized-view.
+pub fn index_ddl(opt: &Opt) -> String {
+    let mut statements = vec![];
+    for column in &opt.secondary_index {
+        statements.push(format!(
+            "CREATE INDEX IF NOT EXISTS <TABLE>_{0}_idx ON <KEYSPACE>.<TABLE> ({0})",
+            column
+        ));
+    }
+    for column in &opt.local_index {
+        statements.push(format!(
+            "CREATE INDEX IF NOT EXISTS <TABLE>_{0}_local_idx ON <KEYSPACE>.<TABLE> ((rack_id, sled_id), {0})",
+            column
+        ));
+    }
+    for column in &opt.materialized_view {
+        statements.push(format!(
+            "CREATE MATERIALIZED VIEW IF NOT EXISTS {} AS
+    SELECT * FROM <KEYSPACE>.<TABLE>
+    WHERE {1} IS NOT NULL AND rack_id IS NOT NULL AND sled_id IS NOT NULL AND time IS NOT NULL
+    PRIMARY KEY ({1}, rack_id, sled_id, time)",
+            view_name(column),
+            column
+        ));
+    }
+    statements.join(";\n")
+}
+
+/// Queries that find a row by an indexed column through each global index and
+/// view, used to measure how far they lag behind the base table. Local indexes
+/// are updated together with the base table, so they have no lag to measure.
+pub fn lagging_queries(opt: &Opt) -> Vec<(String, String)> {
+    let index = opt.secondary_index.iter().map(|column| {
+        (
+            column.clone(),
+            format!("SELECT time FROM <KEYSPACE>.<TABLE> WHERE {} = ?", column),
+        )
+    });
+    let view = opt.materialized_view.iter().map(|column| {
+        (
+            column.clone(),
+            format!(
+                "SELECT time FROM {} WHERE {} = ?",
+                view_name(column),
+                column
+            ),
+        )
+    });
+    index.chain(view).collect()
+}
+
+/// Derives a sled attribute from its id, so each sled keeps the same model,
+/// serial and zone across writes.
+fn sled_attribute(prefix: &str, sled_id: &Uuid, offset: usize) -> String {
+    let simple = sled_id.simple().to_string();
+    format!("{}-{}", prefix, &simple[offset..offset + 8])
+}
+
+/// The value of an indexable column for a sled.
+pub fn sled_column(column: &str, sled_id: &Uuid) -> String {
+    match column {
+        "sled_model" => sled_attribute("m", sled_id, 0),
+        "zone_name" => sled_attribute("z", sled_id, 16),
+        _ => sled_attribute("s", sled_id, 8),
+    }
+}
+
 impl WritePayload for Device {
     fn insert_query() -> &'static str {
         INSERT_DEVICE
@@ -224,15 +365,16 @@ impl WritePayload for Device {
         let mut rng = rand::thread_rng();
         let now = Utc::now();
         let string = Alphanumeric.sample_string(&mut rand::thread_rng(), 4);
+        let sled_id = sled_id(distribution);
         Device {
             kind: "vnic".to_string(),
             link_name: format!("l-{}", string),
-            rack_id: rack_id(distribution),
-            sled_id: sled_id(distribution),
-            sled_model: format!("m-{}", string),
+            rack_id: sled_rack_id(&sled_id),
+            sled_id,
+            sled_model: sled_column("sled_model", &sled_id),
             sled_revision: rng.gen_range(0..10),
-            sled_serial: format!("s-{}", string),
-            zone_name: format!("z-{}", string),
+            sled_serial: sled_column("sled_serial", &sled_id),
+            zone_name: sled_column("zone_name", &sled_id),
             bytes_sent: rng.gen_range(0..1000),
             bytes_received: rng.gen_range(0..1000),
             packets_sent: rng.gen_range(1000..1000000),
@@ -240,6 +382,40 @@ impl WritePayload for Device {
             time: now,
         }
     }
+
+    fn size(&self) -> usize {
+        self.kind.len()
+            + self.link_name.len()
+            + self.sled_model.len()
+            + self.sled_serial.len()
+            + self.zone_name.len()
+            + 16 * 2
+            + 4 * 5
+            + 8
+    }
+
+    /// Rows made in the same loop share a millisecond, the precision `time` is
+    /// stored at, so each row is offset by its position to stay distinct.
+    fn colocate_with(&mut self, other: &Self, position: usize) {
+        self.time = other.time + chrono::Duration::milliseconds(position as i64);
+        self.rack_id = other.rack_id;
+        self.sled_id = other.sled_id;
+        self.sled_model = other.sled_model.clone();
+        self.sled_serial = other.sled_serial.clone();
+        self.zone_name = other.zone_name.clone();
+    }
+}
+
+/// A row for a sled outside the pool, so its indexed values match only this row.
+pub fn probe_device() -> Device {
+    let mut device = Device::insert_values("uniform");
+    let sled_id = Uuid::new_v4();
+    device.rack_id = sled_rack_id(&sled_id);
+    device.sled_id = sled_id;
+    device.sled_model = sled_column("sled_model", &sled_id);
+    device.sled_serial = sled_column("sled_serial", &sled_id);
+    device.zone_name = sled_column("zone_name", &sled_id);
+    device
 }
 
 impl ReadPayload for DeviceValues {
@@ -249,10 +425,65 @@ impl ReadPayload for DeviceValues {
 
     fn select_values(distribution: &str) -> Self {
         let time = Utc::now() - chrono::Duration::seconds(5);
+        let sled_id = sled_id(distribution);
         DeviceValues {
-            rack_id: rack_id(distribution),
-            sled_id: sled_id(distribution),
+            rack_id: sled_rack_id(&sled_id),
+            sled_id,
             time,
         }
     }
 }
+
+impl ReadPayload for DeviceIndexValues {
+    fn select_query() -> &'static str {
+        SELECT_DEVICE_INDEXED.as_str()
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        DeviceIndexValues {
+            value: sled_column(&READ_COLUMN, &sled_id(distribution)),
+        }
+    }
+}
+
+impl ReadPayload for DeviceLocalIndexValues {
+    fn select_query() -> &'static str {
+        SELECT_DEVICE_INDEXED.as_str()
+    }
+
+    fn select_values(distribution: &str) -> Self {
+        let sled_id = sled_id(distribution);
+        DeviceLocalIndexValues {
+            rack_id: sled_rack_id(&sled_id),
+            sled_id,
+            value: sled_column(&READ_COLUMN, &sled_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colocated_batch_has_distinct_primary_keys() {
+        let first = Device::insert_values("uniform");
+        let mut keys = vec![(first.rack_id, first.sled_id, first.time.timestamp_millis())];
+        for position in 1..10 {
+            let mut device = Device::insert_values("uniform");
+            device.colocate_with(&first, position);
+            assert_eq!(
+                (device.rack_id, device.sled_id),
+                (first.rack_id, first.sled_id)
+            );
+            keys.push((
+                device.rack_id,
+                device.sled_id,
+                device.time.timestamp_millis(),
+            ));
+        }
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 10);
+    }
+}
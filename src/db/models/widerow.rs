@@ -0,0 +1,318 @@
+use crate::db::models::timeseries::sled_id;
+use crate::db::models::{ReadPayload, WritePayload};
+use chrono::{DateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::Rng;
+use scylla::frame::response::cql_to_rust::FromRowError;
+use scylla::frame::response::result::Row;
+use scylla::serialize::row::{RowSerializationContext, SerializeRow};
+use scylla::serialize::{RowWriter, SerializationError};
+use scylla::{FromRow, SerializeRow};
+use std::env;
+use uuid::Uuid;
+
+pub const DDL_WIDE_ROW: &str = r#"
+    CREATE KEYSPACE IF NOT EXISTS <KEYSPACE> WITH replication =
+    {'class': 'NetworkTopologyStrategy', 'replication_factor': <RF>}
+    AND tablets = {'enabled': <TABLETS_ENABLED>, 'initial': <TABLETS>};
+
+    USE <KEYSPACE>;
+    CREATE TABLE IF NOT EXISTS <KEYSPACE>.<TABLE>
+    (
+        partition_id uuid,
+        time         timestamp,
+        value        bigint,
+        payload      text,
+        PRIMARY KEY (partition_id, time)
+    ) WITH CLUSTERING ORDER BY (time DESC)<AND_TABLE_OPTIONS>
+"#;
+
+/// Inserted rows expire after `TTL` seconds. When it's 0 the clause is left
+/// out, so the table's default time to live still applies.
+static INSERT_WIDE_ROW: Lazy<String> = Lazy::new(|| {
+    let using_ttl = match env::var("TTL").unwrap_or_default().as_str() {
+        "" | "0" => String::new(),
+        ttl => format!("    USING TTL {}\n", ttl),
+    };
+    format!(
+        "
+    INSERT INTO <KEYSPACE>.<TABLE>
+    (
+        partition_id,
+        time,
+        value,
+        payload
+    )
+    VALUES (?, ?, ?, ?)
+{}",
+        using_ttl
+    )
+});
+
+pub const DELETE_WIDE_ROW: &str = "
+    DELETE FROM <KEYSPACE>.<TABLE>
+    WHERE partition_id = ? AND time = ?
+";
+
+pub const DELETE_WIDE_ROW_RANGE: &str = "
+    DELETE FROM <KEYSPACE>.<TABLE>
+    WHERE partition_id = ? AND time >= ? AND time < ?
+";
+
+pub const DELETE_WIDE_ROW_PARTITION: &str = "
+    DELETE FROM <KEYSPACE>.<TABLE>
+    WHERE partition_id = ?
+";
+
+/// Reads in clustering order, newest first. Used by the latest, window and scan shapes.
+pub const SELECT_WIDE_ROW: &str = "
+    SELECT
+        partition_id,
+        time,
+        value,
+        payload
+    FROM <KEYSPACE>.<TABLE>
+    WHERE partition_id = ? AND time >= ? AND time < ?
+    LIMIT ?
+";
+
+/// Reads against the clustering order, oldest first.
+pub const SELECT_WIDE_ROW_REVERSED: &str = "
+    SELECT
+        partition_id,
+        time,
+        value,
+        payload
+    FROM <KEYSPACE>.<TABLE>
+    WHERE partition_id = ? AND time >= ? AND time < ?
+    ORDER BY time ASC
+    LIMIT ?
+";
+
+/// Clustering rows are spaced a second apart from this instant, so a
+/// partition's row index maps directly onto a time range.
+static BASE_TIME: Lazy<DateTime<Utc>> =
+    Lazy::new(|| Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+
+static ROWS_PER_PARTITION: Lazy<i64> = Lazy::new(|| {
+    env::var("ROWS_PER_PARTITION")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse()
+        .expect("Failed to parse ROWS_PER_PARTITION")
+});
+
+static READ_ROWS: Lazy<i64> = Lazy::new(|| {
+    env::var("READ_ROWS")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .expect("Failed to parse READ_ROWS")
+});
+
+static DELETE_RATIO: Lazy<f64> = Lazy::new(|| {
+    env::var("DELETE_RATIO")
+        .unwrap_or_else(|_| "0".to_string())
+        .parse()
+        .expect("Failed to parse DELETE_RATIO")
+});
+
+static DELETE_OPERATION: Lazy<String> =
+    Lazy::new(|| env::var("DELETE_OPERATION").unwrap_or_else(|_| "row".to_string()));
+
+static READ_SHAPE: Lazy<String> =
+    Lazy::new(|| env::var("READ_SHAPE").unwrap_or_else(|_| "latest".to_string()));
+
+fn row_time(index: i64) -> DateTime<Utc> {
+    *BASE_TIME + chrono::Duration::seconds(index)
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct WideRow {
+    pub partition_id: Uuid,
+    pub time: DateTime<Utc>,
+    pub value: i64,
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct WideRowDelete {
+    pub partition_id: Uuid,
+    pub time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct WideRowDeleteRange {
+    pub partition_id: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, SerializeRow)]
+pub struct WideRowDeletePartition {
+    pub partition_id: Uuid,
+}
+
+/// An insert, or with `DELETE_RATIO` above 0 a row, range or partition delete
+/// chosen by `DELETE_OPERATION`. Rows read back are returned as `Insert`.
+#[derive(Debug, Clone)]
+pub enum WideRowOp {
+    Insert(WideRow),
+    Delete(WideRowDelete),
+    DeleteRange(WideRowDeleteRange),
+    DeletePartition(WideRowDeletePartition),
+}
+
+impl SerializeRow for WideRowOp {
+    fn serialize(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Result<(), SerializationError> {
+        match self {
+            WideRowOp::Insert(insert) => insert.serialize(ctx, writer),
+            WideRowOp::Delete(delete) => delete.serialize(ctx, writer),
+            WideRowOp::DeleteRange(delete) => delete.serialize(ctx, writer),
+            WideRowOp::DeletePartition(delete) => delete.serialize(ctx, writer),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl FromRow for WideRowOp {
+    fn from_row(row: Row) -> Result<Self, FromRowError> {
+        WideRow::from_row(row).map(WideRowOp::Insert)
+    }
+}
+
+#[derive(Debug, Clone, SerializeRow, FromRow)]
+pub struct WideRowValues {
+    partition_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i32,
+}
+
+impl WritePayload for WideRowOp {
+    fn insert_query() -> &'static str {
+        INSERT_WIDE_ROW.as_str()
+    }
+
+    fn write_queries() -> Vec<&'static str> {
+        vec![
+            INSERT_WIDE_ROW.as_str(),
+            DELETE_WIDE_ROW,
+            DELETE_WIDE_ROW_RANGE,
+            DELETE_WIDE_ROW_PARTITION,
+        ]
+    }
+
+    fn write_query_index(&self) -> usize {
+        match self {
+            WideRowOp::Insert(_) => 0,
+            WideRowOp::Delete(_) => 1,
+            WideRowOp::DeleteRange(_) => 2,
+            WideRowOp::DeletePartition(_) => 3,
+        }
+    }
+
+    /// Range deletes cover `READ_ROWS` rows at a random offset, the same span
+    /// as a window read, so reads keep running into the range tombstones.
+    fn insert_values(distribution: &str) -> Self {
+        let mut rng = rand::thread_rng();
+        let partition_id = sled_id(distribution);
+        let index = rng.gen_range(0..*ROWS_PER_PARTITION);
+        if rng.gen_bool(DELETE_RATIO.clamp(0.0, 1.0)) {
+            return match DELETE_OPERATION.as_str() {
+                "range" => WideRowOp::DeleteRange(WideRowDeleteRange {
+                    partition_id,
+                    start: row_time(index),
+                    end: row_time(index + *READ_ROWS),
+                }),
+                "partition" => WideRowOp::DeletePartition(WideRowDeletePartition { partition_id }),
+                _ => WideRowOp::Delete(WideRowDelete {
+                    partition_id,
+                    time: row_time(index),
+                }),
+            };
+        }
+        WideRowOp::Insert(WideRow {
+            partition_id,
+            time: row_time(index),
+            value: rng.gen(),
+            payload: Alphanumeric.sample_string(&mut rng, 32),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            WideRowOp::Insert(insert) => 16 + 8 + 8 + insert.payload.len(),
+            WideRowOp::Delete(_) => 16 + 8,
+            WideRowOp::DeleteRange(_) => 16 + 8 + 8,
+            WideRowOp::DeletePartition(_) => 16,
+        }
+    }
+
+    fn colocate_with(&mut self, other: &Self, _position: usize) {
+        let partition_id = other.partition_id();
+        match self {
+            WideRowOp::Insert(insert) => insert.partition_id = partition_id,
+            WideRowOp::Delete(delete) => delete.partition_id = partition_id,
+            WideRowOp::DeleteRange(delete) => delete.partition_id = partition_id,
+            WideRowOp::DeletePartition(delete) => delete.partition_id = partition_id,
+        }
+    }
+
+    fn is_delete(&self) -> bool {
+        !matches!(self, WideRowOp::Insert(_))
+    }
+}
+
+impl WideRowOp {
+    fn partition_id(&self) -> Uuid {
+        match self {
+            WideRowOp::Insert(insert) => insert.partition_id,
+            WideRowOp::Delete(delete) => delete.partition_id,
+            WideRowOp::DeleteRange(delete) => delete.partition_id,
+            WideRowOp::DeletePartition(delete) => delete.partition_id,
+        }
+    }
+}
+
+impl ReadPayload for WideRowValues {
+    fn select_query() -> &'static str {
+        match READ_SHAPE.as_str() {
+            "reverse" => SELECT_WIDE_ROW_REVERSED,
+            _ => SELECT_WIDE_ROW,
+        }
+    }
+
+    /// latest: the newest `READ_ROWS` rows.
+    /// window: `READ_ROWS` seconds worth of rows starting at a random offset.
+    /// reverse: the oldest `READ_ROWS` rows, read against the clustering order.
+    /// scan: the whole partition.
+    fn select_values(distribution: &str) -> Self {
+        let rows = *ROWS_PER_PARTITION;
+        let read_rows = *READ_ROWS;
+        let (start, end, limit) = match READ_SHAPE.as_str() {
+            "window" => {
+                let offset = rand::thread_rng().gen_range(0..rows.max(1));
+                (offset, offset + read_rows, i32::MAX)
+            }
+            "scan" => (0, rows, i32::MAX),
+            _ => (
+                0,
+                rows,
+                i32::try_from(read_rows).expect("--read-rows is validated to fit in an int"),
+            ),
+        };
+        WideRowValues {
+            partition_id: sled_id(distribution),
+            start: row_time(start),
+            end: row_time(end),
+            limit,
+        }
+    }
+}
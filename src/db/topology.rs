@@ -0,0 +1,133 @@
+use crate::Opt;
+use anyhow::{anyhow, Result};
+use futures::StreamExt;
+use scylla::Session;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const SELECT_INITIAL_TABLETS: &str =
+    "SELECT initial_tablets FROM system_schema.scylla_keyspaces WHERE keyspace_name = ?";
+
+const SELECT_TABLETS: &str =
+    "SELECT table_name, replicas FROM system.tablets WHERE keyspace_name = ? ALLOW FILTERING";
+
+const SELECT_LOCAL_TOKENS: &str = "SELECT host_id, tokens FROM system.local";
+
+const SELECT_PEER_TOKENS: &str = "SELECT host_id, tokens FROM system.peers";
+
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    pub keyspace: String,
+    pub table: String,
+    pub initial_tablets: Option<i32>,
+    pub table_tablets: usize,
+    pub nodes: Vec<NodeLayout>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeLayout {
+    pub host_id: Uuid,
+    pub address: String,
+    pub datacenter: String,
+    pub rack: String,
+    pub shards: Option<u16>,
+    pub tokens: usize,
+    pub tablet_replicas: usize,
+}
+
+/// Reads the actual tablet and vnode layout from the cluster's system tables
+/// and checks it against the tablets requested on the command line.
+pub async fn fetch(session: &Session, opt: &Opt) -> Result<Topology> {
+    let table = opt.table();
+
+    // Clusters without tablets support have no scylla_keyspaces table.
+    let initial_tablets = match session
+        .query_unpaged(SELECT_INITIAL_TABLETS, (&opt.keyspace,))
+        .await
+    {
+        Ok(result) => result
+            .maybe_first_row_typed::<(Option<i32>,)>()
+            .map_err(|e| anyhow!("Error reading keyspace tablets: {}", e))?
+            .and_then(|(initial,)| initial),
+        Err(_) => None,
+    };
+
+    let mut tablets_by_host: HashMap<Uuid, usize> = HashMap::new();
+    let mut table_tablets = 0;
+    if initial_tablets.is_some() {
+        let mut rows = session
+            .query_iter(SELECT_TABLETS, (&opt.keyspace,))
+            .await
+            .map_err(|e| anyhow!("Error reading system.tablets: {}", e))?
+            .into_typed::<(Option<String>, Option<Vec<(Uuid, i32)>>)>();
+
+        while let Some(row) = rows.next().await {
+            let (table_name, replicas) =
+                row.map_err(|e| anyhow!("Error reading system.tablets: {}", e))?;
+            if table_name.as_deref() != Some(table.as_str()) {
+                continue;
+            }
+            table_tablets += 1;
+            for (host_id, _shard) in replicas.unwrap_or_default() {
+                *tablets_by_host.entry(host_id).or_default() += 1;
+            }
+        }
+    }
+
+    let mut tokens_by_host: HashMap<Uuid, usize> = HashMap::new();
+    for query in [SELECT_LOCAL_TOKENS, SELECT_PEER_TOKENS] {
+        let rows = session
+            .query_unpaged(query, &[])
+            .await
+            .map_err(|e| anyhow!("Error reading token ring: {}", e))?
+            .rows_typed_or_empty::<(Option<Uuid>, Option<Vec<String>>)>();
+        for row in rows {
+            let (host_id, tokens) = row.map_err(|e| anyhow!("Error reading token ring: {}", e))?;
+            if let Some(host_id) = host_id {
+                tokens_by_host.insert(host_id, tokens.map(|t| t.len()).unwrap_or(0));
+            }
+        }
+    }
+
+    let nodes = session
+        .get_cluster_data()
+        .get_nodes_info()
+        .iter()
+        .map(|node| NodeLayout {
+            host_id: node.host_id,
+            address: node.address.to_string(),
+            datacenter: node.datacenter.clone().unwrap_or_default(),
+            rack: node.rack.clone().unwrap_or_default(),
+            shards: node.sharder().map(|sharder| sharder.nr_shards.get()),
+            tokens: tokens_by_host.get(&node.host_id).copied().unwrap_or(0),
+            tablet_replicas: tablets_by_host.get(&node.host_id).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let mut warnings = vec![];
+    match initial_tablets {
+        None if opt.tablets > 0 => warnings.push(format!(
+            "Keyspace {} uses vnodes, --tablets {} has no effect. Drop the keyspace or choose another to enable tablets.",
+            opt.keyspace, opt.tablets
+        )),
+        Some(_) if opt.tablets == 0 => warnings.push(format!(
+            "Keyspace {} uses tablets although --tablets is 0. Drop the keyspace or choose another to use vnodes.",
+            opt.keyspace
+        )),
+        Some(initial) if initial != opt.tablets as i32 => warnings.push(format!(
+            "Keyspace {} was created with {} initial tablets, not {}.",
+            opt.keyspace, initial, opt.tablets
+        )),
+        _ => {}
+    }
+
+    Ok(Topology {
+        keyspace: opt.keyspace.clone(),
+        table,
+        initial_tablets,
+        table_tablets,
+        nodes,
+        warnings,
+    })
+}
@@ -1,9 +1,19 @@
+use crate::db::credentials::Secret;
 use crate::db::models::cache::{Cache, CacheValues};
-use crate::db::models::timeseries::{Device, DeviceValues};
+use crate::db::models::collections::{Collections, CollectionsValues};
+use crate::db::models::counter::{self, CounterUpdate, CounterValues};
+use crate::db::models::keyvalue::{self, KeyValue, KeyValueValues};
+use crate::db::models::lwt::{Lwt, LwtValues};
+use crate::db::models::timeseries::{
+    Device, DeviceIndexValues, DeviceLocalIndexValues, DeviceValues,
+};
+use crate::db::models::widerow::{WideRowOp, WideRowValues};
 use anyhow::Result;
 use app::{logging, App};
 use clap::Parser;
+use std::path::PathBuf;
 use std::sync::Arc;
+use tracing::debug;
 
 mod app;
 mod db;
@@ -15,14 +25,27 @@ struct Opt {
     host: String,
 
     /// Username
-    #[structopt(long, default_value = "cassandra")]
-    username: String,
+    /// Falls back to the credentials file, the prompt and then "cassandra".
+    #[structopt(long, env = "SKYLAR_USERNAME")]
+    username: Option<String>,
 
     /// Password
-    #[structopt(long, default_value = "cassandra")]
-    password: String,
+    /// Prefer the environment variable or the credentials file, flags leak into shell history.
+    #[structopt(long, env = "SKYLAR_PASSWORD", hide_env_values = true)]
+    password: Option<Secret>,
+
+    /// Credentials file
+    /// A file of username=... and password=... lines, such as a cqlshrc.
+    #[structopt(long, env = "SKYLAR_CREDENTIALS_FILE")]
+    credentials_file: Option<PathBuf>,
+
+    /// Prompt for the username and password when they are not otherwise provided.
+    #[structopt(long)]
+    prompt_password: bool,
 
     /// Consistency level
+    /// SERIAL and LOCAL_SERIAL set the serial consistency of lightweight
+    /// transactions, with QUORUM and LOCAL_QUORUM respectively for everything else.
     #[structopt(long, short = 'c', default_value = "LOCAL_QUORUM")]
     consistency_level: String,
 
@@ -34,10 +57,108 @@ struct Opt {
     #[structopt(long, short = 'd', default_value = "datacenter1")]
     datacenter: String,
 
+    /// Compression
+    /// The CQL frame compression to negotiate with the cluster: lz4, snappy or none.
+    #[structopt(long, default_value = "none")]
+    compression: String,
+
     /// Number of tablets, if set to 0 tablets are disabled
     #[structopt(long, short = 't', default_value = "3000")]
     tablets: usize,
 
+    /// Keyspace
+    #[structopt(long, short = 'k', default_value = "skylar")]
+    keyspace: String,
+
+    /// Table
+    /// Defaults to the payload's own table name, e.g. devices or cache.
+    #[structopt(long)]
+    table: Option<String>,
+
+    /// Compaction strategy
+    /// The table's compaction class, e.g. SizeTieredCompactionStrategy,
+    /// LeveledCompactionStrategy, TimeWindowCompactionStrategy or IncrementalCompactionStrategy.
+    #[structopt(long)]
+    compaction: Option<String>,
+
+    /// Table compression
+    /// The sstable compressor, e.g. LZ4Compressor, SnappyCompressor, DeflateCompressor,
+    /// ZstdCompressor or none to disable compression.
+    #[structopt(long)]
+    table_compression: Option<String>,
+
+    /// Caching
+    /// all, keys_only or none.
+    #[structopt(long)]
+    caching: Option<String>,
+
+    /// GC grace seconds
+    #[structopt(long)]
+    gc_grace_seconds: Option<u64>,
+
+    /// Default TTL
+    /// The table's default time to live in seconds.
+    #[structopt(long)]
+    default_ttl: Option<u64>,
+
+    /// Bloom filter false positive chance
+    #[structopt(long)]
+    bloom_filter_fp_chance: Option<f64>,
+
+    /// Secondary indexes
+    /// Timeseries columns to create global secondary indexes on, e.g. sled_serial,zone_name.
+    #[structopt(long, value_delimiter = ',')]
+    secondary_index: Vec<String>,
+
+    /// Local indexes
+    /// Timeseries columns to create local secondary indexes on.
+    #[structopt(long, value_delimiter = ',')]
+    local_index: Vec<String>,
+
+    /// Materialized views
+    /// Timeseries columns to create materialized views keyed by.
+    #[structopt(long, value_delimiter = ',')]
+    materialized_view: Vec<String>,
+
+    /// Read path
+    /// How the timeseries payload reads: base (the table), index, local-index or
+    /// view, through the first column given for that index or view.
+    #[structopt(long, default_value = "base")]
+    read_path: String,
+
+    /// Run the schema migration and teardown only, without any load.
+    #[structopt(long)]
+    schema_only: bool,
+
+    /// Skip the schema migration and run against pre-existing tables.
+    #[structopt(long)]
+    skip_migration: bool,
+
+    /// Drop the keyspace at the end of the run.
+    #[structopt(long, conflicts_with = "truncate")]
+    drop_keyspace: bool,
+
+    /// Truncate the table at the end of the run.
+    #[structopt(long)]
+    truncate: bool,
+
+    /// Batch size
+    /// The number of rows each write request carries. 1 disables batching.
+    #[structopt(long, default_value = "1")]
+    batch_size: usize,
+
+    /// Batch type
+    /// logged, unlogged or counter. The counter payload requires counter batches.
+    #[structopt(long, default_value = "unlogged")]
+    batch_type: String,
+
+    /// Batch grouping
+    /// partition groups each batch's rows under one partition key, spread
+    /// leaves them across partitions. Only the timeseries and widerow payloads
+    /// have several rows per partition to group, and lwt can't be batched.
+    #[structopt(long, default_value = "partition")]
+    batch_grouping: String,
+
     /// Number of read threads
     #[structopt(long, short = 'R', default_value = "50")]
     readers: usize,
@@ -46,10 +167,123 @@ struct Opt {
     #[structopt(long, short = 'W', default_value = "50")]
     writers: usize,
 
+    /// Retries
+    /// How many times a failed read or statement preparation is retried before
+    /// the worker gives up on it and moves on.
+    #[structopt(long, default_value = "3")]
+    retries: u32,
+
+    /// Retry backoff
+    /// The delay in milliseconds before the first retry, doubling with each retry after it.
+    #[structopt(long, default_value = "100")]
+    retry_backoff_ms: u64,
+
+    /// Retry backoff max
+    /// The longest delay in milliseconds between retries.
+    #[structopt(long, default_value = "5000")]
+    retry_backoff_max_ms: u64,
+
     /// Payload type
+    /// timeseries, cache, keyvalue, widerow, lwt, counter or collections.
     #[structopt(long, short = 'P', default_value = "timeseries")]
     payload: String,
 
+    /// Value type
+    /// The column type of the keyvalue payload's values: blob or text.
+    #[structopt(long, default_value = "blob")]
+    value_type: String,
+
+    /// Value size
+    /// The size of the keyvalue payload's values. Either fixed, e.g. 1KB, a uniform
+    /// range, e.g. 1KB..10KB, or a histogram, e.g. "80% 1KB, 19% 10KB, 1% 1MB".
+    #[structopt(long, default_value = "1KB")]
+    value_size: String,
+
+    /// Rows per partition
+    /// The number of clustering rows each widerow partition accumulates.
+    #[structopt(long, default_value = "1000")]
+    rows_per_partition: u64,
+
+    /// Read shape
+    /// The widerow payload's read query: latest (the newest rows with LIMIT),
+    /// window (a time window at a random offset), reverse (the oldest rows,
+    /// against the clustering order) or scan (the whole partition).
+    #[structopt(long, default_value = "latest")]
+    read_shape: String,
+
+    /// Read rows
+    /// The number of rows the latest, reverse and window read shapes cover, and
+    /// the LIMIT of timeseries reads through an index or view.
+    #[structopt(long, default_value = "100")]
+    read_rows: u64,
+
+    /// Delete ratio
+    /// The fraction of widerow writes that are deletes, from 0 to 1.
+    #[structopt(long, default_value = "0")]
+    delete_ratio: f64,
+
+    /// Delete operation
+    /// The widerow payload's delete: row, range (a clustering range the size
+    /// of --read-rows) or partition.
+    #[structopt(long, default_value = "row")]
+    delete_operation: String,
+
+    /// TTL
+    /// The time to live in seconds of rows inserted by the widerow payload, 0 leaves
+    /// them to the table's default time to live.
+    #[structopt(long, default_value = "0")]
+    ttl: u64,
+
+    /// Collection size
+    /// The number of elements the collections payload writes into each collection on insert.
+    #[structopt(long, default_value = "10")]
+    collection_size: usize,
+
+    /// Collection operation
+    /// The collections payload's write: insert (whole rows), append (to the
+    /// non-frozen collections), update (single elements) or mixed.
+    #[structopt(long, default_value = "mixed")]
+    collection_operation: String,
+
+    /// Page size
+    /// The number of rows fetched per page by reads.
+    #[structopt(long, default_value = "5000")]
+    page_size: i32,
+
+    /// LWT operation
+    /// The lwt payload's conditional write: insert (IF NOT EXISTS), update
+    /// (compare-and-set), delete (conditional delete) or mixed.
+    #[structopt(long, default_value = "mixed")]
+    lwt_operation: String,
+
+    /// Validate reads
+    /// Check that keyvalue reads return the last acknowledged write, counting
+    /// missing rows, stale values and corrupt values.
+    #[structopt(long)]
+    validate: bool,
+
+    /// Verify writes
+    /// After a keyvalue run, save every key written to --verify-file, re-read
+    /// them and report acknowledged writes that are missing and failed writes
+    /// that are present.
+    #[structopt(long)]
+    verify: bool,
+
+    /// Verify only
+    /// Skip the load and verify the keys an earlier --verify run saved to --verify-file.
+    #[structopt(long)]
+    verify_only: bool,
+
+    /// Verify file
+    /// The file --verify saves written keys to and --verify-only reads them from.
+    #[structopt(long, default_value = "written-keys.txt")]
+    verify_file: PathBuf,
+
+    /// Verify consistency level
+    /// The consistency level verification reads at, defaulting to --consistency-level.
+    #[structopt(long)]
+    verify_consistency_level: Option<String>,
+
     /// Cardinality
     /// The number of unique values to generate.
     #[structopt(long, short = 'C', default_value = "1000000")]
@@ -95,31 +329,149 @@ struct Opt {
     /// The period over which to increase the rate from rate_min to rate_max.
     #[structopt(long, default_value = "0")]
     rate_period: u64,
+
+    /// Results file
+    /// Write a JSON summary of the run, including error counts, to this file when it ends.
+    #[structopt(long)]
+    results_file: Option<PathBuf>,
+
+    /// Trace probability
+    /// The fraction of requests to enable CQL tracing on, between 0 and 1.
+    #[structopt(long, default_value = "0")]
+    trace_probability: f64,
+
+    /// Trace threshold
+    /// Only keep traces of requests slower than this many milliseconds. Without
+    /// --trace-probability every request is traced, which adds considerable load to the cluster.
+    #[structopt(long)]
+    trace_threshold_ms: Option<u64>,
+
+    /// Traces kept
+    /// How many of the slowest traced requests to keep for the TRACES tab and --traces-file.
+    #[structopt(long, default_value = "20")]
+    traces_kept: usize,
+
+    /// Traces file
+    /// Write the slowest traced requests, with their events from system_traces, as JSON to this file when the run ends.
+    #[structopt(long)]
+    traces_file: Option<PathBuf>,
+
+    /// Log file
+    /// Append log events to this file as well as the LOGS tab, filtered by RUST_LOG like them.
+    #[structopt(long)]
+    log_file: Option<PathBuf>,
+
+    /// Log format
+    /// The format of --log-file: text or json.
+    #[structopt(long, default_value = "text")]
+    log_format: String,
+}
+
+impl Opt {
+    /// The table name, defaulting to the one the payload is known by.
+    fn table(&self) -> String {
+        self.table.clone().unwrap_or_else(|| {
+            match self.payload.as_str() {
+                "cache" => "cache",
+                "keyvalue" => "keyvalue",
+                "widerow" => "widerow",
+                "lwt" => "lwt",
+                "counter" => "counter",
+                "collections" => "collections",
+                _ => "devices",
+            }
+            .to_string()
+        })
+    }
+
+    /// The indexed column the read path goes through, if it doesn't read the base table.
+    fn read_column(&self) -> Option<&String> {
+        match self.read_path.as_str() {
+            "index" => self.secondary_index.first(),
+            "local-index" => self.local_index.first(),
+            "view" => self.materialized_view.first(),
+            _ => None,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let opt = Opt::parse();
     dotenv::dotenv().ok();
-    logging::init();
+    let opt = Opt::parse();
+    logging::init(&opt)?;
+
+    debug!("Run configuration: {:?}", opt);
 
     std::env::set_var("CARDINALITY", opt.cardinality.to_string());
+    std::env::set_var("VALUE_TYPE", &opt.value_type);
+    std::env::set_var("VALUE_SIZE", &opt.value_size);
+    std::env::set_var("ROWS_PER_PARTITION", opt.rows_per_partition.to_string());
+    std::env::set_var("READ_SHAPE", &opt.read_shape);
+    std::env::set_var("READ_ROWS", opt.read_rows.to_string());
+    std::env::set_var("LWT_OPERATION", &opt.lwt_operation);
+    std::env::set_var("DELETE_RATIO", opt.delete_ratio.to_string());
+    std::env::set_var("DELETE_OPERATION", &opt.delete_operation);
+    std::env::set_var("TTL", opt.ttl.to_string());
+    std::env::set_var("COLLECTION_SIZE", opt.collection_size.to_string());
+    std::env::set_var("COLLECTION_OPERATION", &opt.collection_operation);
+    std::env::set_var("VERIFY", opt.verify.to_string());
+    std::env::set_var("READ_PATH", &opt.read_path);
+    if let Some(column) = opt.read_column() {
+        std::env::set_var("READ_COLUMN", column);
+    }
 
-    let session = db::connection::builder(true, &opt).await?;
+    let session = Arc::new(db::connection::builder(!opt.skip_migration, &opt).await?);
 
-    let mut app = App::new();
+    if opt.verify_only {
+        let keys = keyvalue::load_written_keys(&opt)?;
+        keyvalue::verify(&session, &opt, keys).await?;
+    } else if !opt.schema_only {
+        let mut app = App::new();
 
-    let result = match opt.payload.as_str() {
-        "timeseries" => {
-            app.run::<Device, DeviceValues>(Arc::from(session), &opt)
-                .await
-        }
-        "cache" => {
-            app.run::<Cache, CacheValues>(Arc::from(session), &opt)
-                .await
-        }
-        _ => panic!("Unsupported payload type"),
-    };
+        match opt.payload.as_str() {
+            "timeseries" => match opt.read_path.as_str() {
+                "index" | "view" => {
+                    app.run::<Device, DeviceIndexValues>(session.clone(), &opt)
+                        .await?
+                }
+                "local-index" => {
+                    app.run::<Device, DeviceLocalIndexValues>(session.clone(), &opt)
+                        .await?
+                }
+                _ => {
+                    app.run::<Device, DeviceValues>(session.clone(), &opt)
+                        .await?
+                }
+            },
+            "cache" => app.run::<Cache, CacheValues>(session.clone(), &opt).await?,
+            "keyvalue" => {
+                app.run::<KeyValue, KeyValueValues>(session.clone(), &opt)
+                    .await?;
+                if opt.verify {
+                    let keys = keyvalue::written_keys();
+                    keyvalue::save_written_keys(&opt, &keys)?;
+                    keyvalue::verify(&session, &opt, keys).await?
+                }
+            }
+            "widerow" => {
+                app.run::<WideRowOp, WideRowValues>(session.clone(), &opt)
+                    .await?
+            }
+            "lwt" => app.run::<Lwt, LwtValues>(session.clone(), &opt).await?,
+            "counter" => {
+                counter::check_empty(&session, &opt).await?;
+                app.run::<CounterUpdate, CounterValues>(session.clone(), &opt)
+                    .await?;
+                counter::validate(&session, &opt).await?
+            }
+            "collections" => {
+                app.run::<Collections, CollectionsValues>(session.clone(), &opt)
+                    .await?
+            }
+            _ => panic!("Unsupported payload type"),
+        };
+    }
 
-    result
+    db::connection::teardown(&session, &opt).await
 }